@@ -0,0 +1,286 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! `#[derive_assumptions]`: generates the code that passes a struct's flux
+//! invariants to the compiler.
+//!
+//! The attribute reads the `#[flux_rs::refined_by(...)]`,
+//! `#[flux_rs::invariant(...)]` and `#[field(...)]` annotations of a struct,
+//! and generates an `assert_invariants(&self)` method. For each invariant, the
+//! method calls `assert_invariant!(feature, condition, message...)`, with the
+//! refinement parameters replaced by the fields they index:
+//!
+//! - a slice (or a reference to one) indexed by `n` becomes `self.field.len()`;
+//! - an integer indexed by `n` becomes `self.field as usize`.
+//!
+//! The const generic parameters of the struct (e.g. the length `N` of an array
+//! field) can also be used in the invariants, and are left as they are.
+//!
+//! `assert_invariant!` must be in scope where the struct is defined, and
+//! decides what to do with each fact depending on `feature` (see
+//! `collections/mod.rs`). By default every fact is gated on the
+//! `assume-invariants` feature; `#[derive_assumptions(features = [...])]`
+//! gives one feature per invariant instead, in declaration order.
+//!
+//! The attribute must be placed before the flux attributes, so that it sees
+//! them before they are expanded.
+
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Ident, LitStr, Token};
+
+const DEFAULT_FEATURE: &str = "assume-invariants";
+
+struct Args {
+    features: Vec<LitStr>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(Args { features: Vec::new() });
+        }
+        let key: Ident = input.parse()?;
+        if key != "features" {
+            return Err(syn::Error::new(key.span(), "expected `features = [...]`"));
+        }
+        input.parse::<Token![=]>()?;
+        let content;
+        syn::bracketed!(content in input);
+        let features = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+        Ok(Args {
+            features: features.into_iter().collect(),
+        })
+    }
+}
+
+/// How a refinement parameter is computed from the struct's fields.
+struct Param {
+    name: String,
+    expr: TokenStream,
+}
+
+#[proc_macro_attribute]
+pub fn derive_assumptions(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(attr as Args);
+    let input = parse_macro_input!(item as DeriveInput);
+    match expand(args, &input) {
+        Ok(generated) => quote!(#input #generated).into(),
+        Err(err) => {
+            let err = err.to_compile_error();
+            quote!(#input #err).into()
+        }
+    }
+}
+
+fn expand(args: Args, input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(Span::call_site(), "`derive_assumptions` only supports structs"));
+    };
+
+    let invariants: Vec<&Attribute> = input
+        .attrs
+        .iter()
+        .filter(|attr| is_flux_attr(attr, "invariant"))
+        .collect();
+    if !args.features.is_empty() && args.features.len() != invariants.len() {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            format!(
+                "expected one feature per invariant ({}), got {}",
+                invariants.len(),
+                args.features.len()
+            ),
+        ));
+    }
+
+    let mut params = Vec::new();
+    for field in &data.fields {
+        let Some(attr) = field.attrs.iter().find(|attr| is_flux_attr(attr, "field")) else {
+            continue;
+        };
+        let Some(ident) = &field.ident else {
+            return Err(syn::Error::new_spanned(field, "tuple structs are not supported"));
+        };
+        let tokens = attr.meta.require_list()?.tokens.clone();
+        if let Some(param) = field_param(ident, tokens)? {
+            params.push(param);
+        }
+    }
+
+    let consts: Vec<String> = input
+        .generics
+        .const_params()
+        .map(|param| param.ident.to_string())
+        .collect();
+
+    let name = &input.ident;
+    let mut checks = Vec::new();
+    for (i, attr) in invariants.iter().enumerate() {
+        let tokens = attr.meta.require_list()?.tokens.clone();
+        let mut used = Vec::new();
+        let cond = substitute(tokens.clone(), &params, &consts, &mut used)?;
+        let feature = args
+            .features
+            .get(i)
+            .cloned()
+            .unwrap_or_else(|| LitStr::new(DEFAULT_FEATURE, Span::call_site()));
+
+        // The message is a format string: escape the braces of the invariant.
+        let invariant = tokens.to_string().replace('{', "{{").replace('}', "}}");
+        let mut message = format!("{} invariant violated: {}", name, invariant);
+        let values: Vec<&TokenStream> = used.iter().map(|p: &&Param| &p.expr).collect();
+        if !used.is_empty() {
+            let names: Vec<String> = used.iter().map(|p| format!("{} = {{}}", p.name)).collect();
+            message = format!("{} ({})", message, names.join(", "));
+        }
+        checks.push(quote! {
+            assert_invariant!(#feature, #cond, #message #(, #values)*);
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Passes the flux invariants of this struct to the compiler (or
+            /// checks them), depending on the enabled features.
+            #[inline(always)]
+            #[allow(dead_code, clippy::unnecessary_cast)]
+            fn assert_invariants(&self) {
+                #(#checks)*
+            }
+        }
+    })
+}
+
+/// Whether `attr` is `#[flux_rs::name(...)]`, `#[flux::name(...)]` or `#[name(...)]`.
+fn is_flux_attr(attr: &Attribute, name: &str) -> bool {
+    let segments: Vec<String> = attr
+        .path()
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect();
+    match segments.as_slice() {
+        [n] => n == name,
+        [krate, n] => (krate == "flux_rs" || krate == "flux") && n == name,
+        _ => false,
+    }
+}
+
+/// Parses a `#[field(...)]` annotation such as `{usize[hd] | hd < ring_len}`
+/// or `&mut [T][ring_len]`, and returns the refinement parameter it binds.
+fn field_param(field: &Ident, tokens: TokenStream) -> syn::Result<Option<Param>> {
+    let mut tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    // Unwrap `{ ty | predicate }`.
+    if let [TokenTree::Group(group)] = tokens.as_slice() {
+        if group.delimiter() == Delimiter::Brace {
+            tokens = group
+                .stream()
+                .into_iter()
+                .take_while(|t| !matches!(t, TokenTree::Punct(p) if p.as_char() == '|'))
+                .collect();
+        }
+    }
+
+    // The index is the last bracketed group, e.g. `[hd]` or `[@hd]`.
+    let Some(TokenTree::Group(index)) = tokens.last() else {
+        return Ok(None);
+    };
+    if index.delimiter() != Delimiter::Bracket {
+        return Ok(None);
+    }
+    let name = index
+        .stream()
+        .into_iter()
+        .filter_map(|t| match t {
+            TokenTree::Ident(ident) => Some(ident.to_string()),
+            _ => None,
+        })
+        .next();
+    let Some(name) = name else {
+        return Ok(None);
+    };
+
+    let ty = &tokens[..tokens.len() - 1];
+    let is_slice = ty.iter().any(|t| matches!(t, TokenTree::Group(g) if g.delimiter() == Delimiter::Bracket));
+    let expr = match ty {
+        _ if is_slice => quote!(self.#field.len()),
+        [TokenTree::Ident(int)] if is_integer(&int.to_string()) => quote!((self.#field as usize)),
+        _ => {
+            return Err(syn::Error::new(
+                field.span(),
+                format!(
+                    "unsupported refined field type `{}`: expected a slice or an integer",
+                    ty.iter().cloned().collect::<TokenStream>()
+                ),
+            ))
+        }
+    };
+    Ok(Some(Param { name, expr }))
+}
+
+fn is_integer(ty: &str) -> bool {
+    matches!(
+        ty,
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize"
+    )
+}
+
+/// Replaces the refinement parameters in a flux expression by the Rust
+/// expressions computing them, and records which ones were used. The const
+/// generic parameters in `consts` are kept.
+fn substitute<'p>(
+    tokens: TokenStream,
+    params: &'p [Param],
+    consts: &[String],
+    used: &mut Vec<&'p Param>,
+) -> syn::Result<TokenStream> {
+    let mut out = TokenStream::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if consts.iter().any(|c| ident == c) => {
+                out.extend([TokenTree::Ident(ident)]);
+            }
+            TokenTree::Ident(ident) => {
+                let Some(param) = params.iter().find(|p| ident == p.name) else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("`{}` is not a refinement parameter bound by a `#[field]`", ident),
+                    ));
+                };
+                if !used.iter().any(|p| p.name == param.name) {
+                    used.push(param);
+                }
+                param.expr.to_tokens(&mut out);
+            }
+            TokenTree::Group(group) => {
+                let inner = substitute(group.stream(), params, consts, used)?;
+                let mut new = proc_macro2::Group::new(group.delimiter(), inner);
+                new.set_span(group.span());
+                out.extend([TokenTree::Group(new)]);
+            }
+            TokenTree::Punct(punct) => {
+                if punct.as_char() == '=' {
+                    if let Some(TokenTree::Punct(next)) = tokens.peek() {
+                        if next.as_char() == '>' {
+                            return Err(syn::Error::new(
+                                punct.span(),
+                                "implications are not supported in invariants",
+                            ));
+                        }
+                    }
+                }
+                out.extend([TokenTree::Punct(punct)]);
+            }
+            literal => out.extend([literal]),
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,216 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Runs the same operations on a `RingBuffer` built with `assume-invariants`
+//! and on one built without, and panics as soon as they differ: a wrong
+//! assumption would otherwise silently miscompile.
+//!
+//! The input is decoded as:
+//! - one byte for the length of the ring (`2 + byte % 31`);
+//! - one byte for the offset of the head (and tail) in the ring;
+//! - then two bytes per operation: the operation (`byte % 20`, see `Op`) and
+//!   its argument.
+//!
+//! `corpus/variants` has seeds for the scenarios of the unit tests, which
+//! `make_corpus.py` generates.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Enqueue(i32),
+    Push(i32),
+    Dequeue,
+    // Removes the first element equal to the value.
+    RemoveFirstMatching(i32),
+    // Keeps the elements that are not multiples of the value.
+    Retain(i32),
+    Empty,
+    AsSlices,
+    // Increments every element, and keeps the ones that are not multiples of
+    // the value.
+    RetainMut(i32),
+    // Finds the first element equal to the value.
+    Position(i32),
+    // Inserts the value at an index, which may be out of bounds.
+    Insert(usize, i32),
+    Remove(usize),
+    SwapRemoveBack(usize),
+    SwapRemoveFront(usize),
+    Truncate(usize),
+    TruncateFront(usize),
+    // Moves the elements from an index on to an empty buffer of
+    // `SCRATCH_LEN` slots.
+    SplitOffInto(usize),
+    // Extracts the elements that are multiples of the value.
+    ExtractIf(i32),
+    // Removes the first element equal to the value, moving the last one in
+    // its place.
+    RemoveFirstMatchingUnordered(i32),
+    // Reads the front through a `NonEmpty` guard, then dequeues it.
+    NonEmpty,
+    // Enqueues the value through a `NotFull` guard.
+    NotFull(i32),
+}
+
+// The length of the ring that `Op::SplitOffInto` moves elements to, shorter
+// than most rings so that it can fail.
+const SCRATCH_LEN: usize = 8;
+
+impl Op {
+    fn decode(op: u8, arg: u8) -> Op {
+        let val = i32::from(arg);
+        // The rings have at most 32 slots.
+        let index = usize::from(arg % 32);
+        match op % 20 {
+            0 => Op::Enqueue(val),
+            1 => Op::Push(val),
+            2 => Op::Dequeue,
+            3 => Op::RemoveFirstMatching(val),
+            4 => Op::Retain(val % 7 + 2),
+            5 => Op::Empty,
+            6 => Op::AsSlices,
+            7 => Op::RetainMut(val % 7 + 2),
+            8 => Op::Position(val),
+            9 => Op::Insert(index, val),
+            10 => Op::Remove(index),
+            11 => Op::SwapRemoveBack(index),
+            12 => Op::SwapRemoveFront(index),
+            13 => Op::Truncate(index),
+            14 => Op::TruncateFront(index),
+            15 => Op::SplitOffInto(index),
+            16 => Op::ExtractIf(val % 7 + 2),
+            17 => Op::RemoveFirstMatchingUnordered(val),
+            18 => Op::NonEmpty,
+            _ => Op::NotFull(val),
+        }
+    }
+}
+
+// The result of an operation, followed by the state of the buffer after it.
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    result: Value,
+    len: usize,
+    available_len: usize,
+    has_elements: bool,
+    is_full: bool,
+    front: Option<Vec<i32>>,
+    back: Option<Vec<i32>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Bool(bool),
+    Element(Option<i32>),
+    Count(usize),
+    Index(Option<usize>),
+    Inserted(Result<(), i32>),
+    Elements(Vec<i32>),
+    // Whether `split_off_into` succeeded, and the elements it moved.
+    Split(bool, Vec<i32>),
+    None,
+}
+
+// Implements `$name(buf, op)` for the `RingBuffer` of the crate `$krate`.
+macro_rules! apply {
+    ($name:ident, $krate:ident) => {
+        fn $name(buf: &mut $krate::collections::ring_buffer::RingBuffer<i32>, op: Op) -> Outcome {
+            use $krate::collections::queue::Queue;
+            use $krate::collections::ring_buffer::RingBuffer;
+
+            fn to_vec(buf: &RingBuffer<i32>) -> Vec<i32> {
+                let (front, back) = buf.as_slices();
+                [front.unwrap_or(&[]), back.unwrap_or(&[])].concat()
+            }
+
+            let result = match op {
+                Op::Enqueue(val) => Value::Bool(buf.enqueue(val)),
+                Op::Push(val) => Value::Element(buf.push(val)),
+                Op::Dequeue => Value::Element(buf.dequeue()),
+                Op::RemoveFirstMatching(val) => {
+                    Value::Element(buf.remove_first_matching(|&x| x == val))
+                }
+                Op::Retain(div) => Value::Count(buf.retain(|&x| x % div != 0)),
+                Op::Empty => {
+                    buf.empty();
+                    Value::None
+                }
+                Op::AsSlices => Value::None,
+                Op::RetainMut(div) => Value::Count(buf.retain_mut(|x| {
+                    *x += 1;
+                    *x % div != 0
+                })),
+                Op::Position(val) => Value::Index(buf.position(|&x| x == val)),
+                Op::Insert(index, val) => Value::Inserted(buf.insert(index, val)),
+                Op::Remove(index) => Value::Element(buf.remove(index)),
+                Op::SwapRemoveBack(index) => Value::Element(buf.swap_remove_back(index)),
+                Op::SwapRemoveFront(index) => Value::Element(buf.swap_remove_front(index)),
+                Op::Truncate(len) => {
+                    buf.truncate(len);
+                    Value::None
+                }
+                Op::TruncateFront(len) => {
+                    buf.truncate_front(len);
+                    Value::None
+                }
+                Op::SplitOffInto(at) => {
+                    let mut ring = [0; SCRATCH_LEN];
+                    let mut other = RingBuffer::new(&mut ring);
+                    let split = buf.split_off_into(at, &mut other);
+                    Value::Split(split, to_vec(&other))
+                }
+                Op::ExtractIf(div) => Value::Elements(buf.extract_if(|&x| x % div == 0).collect()),
+                Op::RemoveFirstMatchingUnordered(val) => {
+                    Value::Element(buf.remove_first_matching_unordered(|&x| x == val))
+                }
+                Op::NonEmpty => match buf.non_empty() {
+                    Some(guard) => {
+                        let front = *guard.front();
+                        Value::Elements(vec![front, guard.dequeue()])
+                    }
+                    None => Value::Element(None),
+                },
+                Op::NotFull(val) => Value::Bool(buf.not_full().map(|guard| guard.enqueue(val)).is_some()),
+            };
+            let (front, back) = buf.as_slices();
+            Outcome {
+                result,
+                len: buf.len(),
+                available_len: buf.available_len(),
+                has_elements: buf.has_elements(),
+                is_full: buf.is_full(),
+                front: front.map(<[i32]>::to_vec),
+                back: back.map(<[i32]>::to_vec),
+            }
+        }
+    };
+}
+
+apply!(apply_assumed, assumed);
+apply!(apply_unassumed, unassumed);
+
+fuzz_target!(|data: &[u8]| {
+    let [len, offset, ops @ ..] = data else {
+        return;
+    };
+    let len = 2 + usize::from(*len) % 31;
+    let offset = usize::from(*offset) % len;
+
+    let mut assumed_ring = vec![0; len];
+    let mut unassumed_ring = vec![0; len];
+    let mut assumed =
+        assumed::collections::ring_buffer::RingBuffer::try_from_parts(&mut assumed_ring, offset, offset)
+            .unwrap();
+    let mut unassumed =
+        unassumed::collections::ring_buffer::RingBuffer::try_from_parts(&mut unassumed_ring, offset, offset)
+            .unwrap();
+
+    for (step, op) in ops.chunks_exact(2).enumerate() {
+        let op = Op::decode(op[0], op[1]);
+        let expected = apply_unassumed(&mut unassumed, op);
+        let actual = apply_assumed(&mut assumed, op);
+        assert_eq!(actual, expected, "step {}: {:?}", step, op);
+    }
+});
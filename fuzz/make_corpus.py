@@ -0,0 +1,106 @@
+#!/usr/bin/env python3
+
+"""
+Writes the seed corpus of the `variants` fuzz target to `corpus/variants`, with
+one input per scenario of the unit tests in `src/collections/ring_buffer.rs`,
+and one per group of the other operations.
+
+See `fuzz_targets/variants.rs` for the format of the inputs.
+"""
+import os
+
+(
+    ENQUEUE,
+    PUSH,
+    DEQUEUE,
+    REMOVE_FIRST_MATCHING,
+    RETAIN,
+    EMPTY,
+    AS_SLICES,
+    RETAIN_MUT,
+    POSITION,
+    INSERT,
+    REMOVE,
+    SWAP_REMOVE_BACK,
+    SWAP_REMOVE_FRONT,
+    TRUNCATE,
+    TRUNCATE_FRONT,
+    SPLIT_OFF_INTO,
+    EXTRACT_IF,
+    REMOVE_FIRST_MATCHING_UNORDERED,
+    NON_EMPTY,
+    NOT_FULL,
+) = range(20)
+
+CORPUS_DIR = os.path.join(os.path.dirname(os.path.abspath(__file__)), "corpus", "variants")
+
+
+def encode(ring_len, offset, ops):
+    assert 2 <= ring_len <= 32
+    data = [ring_len - 2, offset]
+    for op in ops:
+        op, arg = op if isinstance(op, tuple) else (op, 0)
+        data += [op, arg]
+    return bytes(data)
+
+
+def enqueue_iota(ring_len):
+    # One more than fits, which must fail.
+    return [(ENQUEUE, i) for i in range(1, ring_len)] + [(ENQUEUE, 0)]
+
+
+def dequeue_iota(ring_len):
+    # One more than there is, which must return `None`.
+    return [DEQUEUE] * ring_len
+
+
+# Retain(div) keeps the elements that are not multiples of `div % 7 + 2`, and
+# ExtractIf(div) extracts the others.
+KEEP_ODD = 0
+
+LEN = 10
+SCENARIOS = {
+    "enqueue_dequeue": encode(LEN, 0, [(ENQUEUE, 42), DEQUEUE] * (2 * LEN)),
+    "push": encode(LEN + 1, 0, [(PUSH, i) for i in range(100)] + dequeue_iota(LEN + 1)),
+    "fill_once": encode(LEN, 0, enqueue_iota(LEN) + dequeue_iota(LEN)),
+    "refill": encode(LEN, 0, (enqueue_iota(LEN) + dequeue_iota(LEN)) * 10),
+    "retain": encode(
+        LEN, LEN - 2, enqueue_iota(LEN) + [(RETAIN, KEEP_ODD), AS_SLICES] + dequeue_iota(LEN)
+    ),
+    "remove_first_matching": encode(
+        LEN, LEN - 2, enqueue_iota(LEN) + [(REMOVE_FIRST_MATCHING, i) for i in (1, 5, 9, 9)] + [AS_SLICES]
+    ),
+    "as_slices": encode(
+        LEN, 0, enqueue_iota(LEN) + [AS_SLICES] + [DEQUEUE] * 3 + [(ENQUEUE, 10), AS_SLICES, (ENQUEUE, 11), AS_SLICES]
+    ),
+    "empty": encode(LEN, 3, enqueue_iota(LEN) + [EMPTY, AS_SLICES] + enqueue_iota(LEN)),
+    "retain_mut": encode(LEN, LEN - 2, enqueue_iota(LEN) + [(RETAIN_MUT, KEEP_ODD), AS_SLICES] + dequeue_iota(LEN)),
+    "position": encode(LEN, LEN - 2, enqueue_iota(LEN) + [(POSITION, i) for i in (1, 5, 9, 10)]),
+    # Indices past the end, which must fail, then on both sides of the middle.
+    "insert_remove": encode(
+        LEN,
+        LEN - 2,
+        [(ENQUEUE, i) for i in range(1, 5)]
+        + [(INSERT, 5), (INSERT, 0), (INSERT, 2), (INSERT, 4), (INSERT, 6)]
+        + [(REMOVE, 7), (REMOVE, 1), (REMOVE, 5), (REMOVE, 0), AS_SLICES],
+    ),
+    "swap_remove": encode(
+        LEN,
+        LEN - 2,
+        enqueue_iota(LEN) + [(SWAP_REMOVE_BACK, 1), (SWAP_REMOVE_FRONT, 5), (SWAP_REMOVE_BACK, 9)]
+        + [(SWAP_REMOVE_FRONT, 0), (REMOVE_FIRST_MATCHING_UNORDERED, 4), AS_SLICES],
+    ),
+    "truncate": encode(LEN, LEN - 2, enqueue_iota(LEN) + [(TRUNCATE, 7), (TRUNCATE_FRONT, 4), (TRUNCATE, 9)]),
+    # Too many elements for the scratch buffer, then few enough.
+    "split_off_into": encode(
+        LEN + 10, LEN, enqueue_iota(LEN + 10) + [(SPLIT_OFF_INTO, 0), (SPLIT_OFF_INTO, 12), (SPLIT_OFF_INTO, 20)]
+    ),
+    "extract_if": encode(LEN, LEN - 2, enqueue_iota(LEN) + [(EXTRACT_IF, KEEP_ODD), AS_SLICES]),
+    "guards": encode(LEN, LEN - 2, [(NOT_FULL, i) for i in range(LEN)] + [NON_EMPTY] * LEN),
+}
+
+if __name__ == "__main__":
+    os.makedirs(CORPUS_DIR, exist_ok=True)
+    for name, data in SCENARIOS.items():
+        with open(os.path.join(CORPUS_DIR, name), "wb") as f:
+            f.write(data)
@@ -0,0 +1,83 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Enables the features of `ring-buffer-smoketest` for the variant crates.
+//!
+//! The variants can't depend on the root crate with different features, since
+//! Cargo would unify them into a single build, and they don't copy its
+//! `[features]` table either, which would drift from it. Instead, this reads
+//! the table from the root `Cargo.toml`: every feature is declared to
+//! `check-cfg`, and the ones of the variant (see `FEATURES`) are enabled, with
+//! the features they imply.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// The features of `ring-buffer-smoketest` that each variant enables.
+const FEATURES: &[(&str, &[&str])] = &[
+    ("ring-buffer-assumed", &["assume-invariants"]),
+    ("ring-buffer-unassumed", &[]),
+];
+
+// Parses the `[features]` table of a manifest into each feature and the
+// features it implies.
+fn parse_features(manifest: &str) -> BTreeMap<String, Vec<String>> {
+    let mut features = BTreeMap::new();
+    let mut in_table = false;
+    let mut current = None;
+    for line in manifest.lines() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.starts_with('[') && current.is_none() {
+            in_table = line == "[features]";
+            continue;
+        }
+        if !in_table || line.is_empty() {
+            continue;
+        }
+        // Either the continuation of a multi-line array, or a new feature.
+        let (name, values) = match current.take() {
+            Some(name) => (name, line),
+            None => {
+                let (name, values) = line.split_once('=').expect("feature without a value");
+                (name.trim().to_owned(), values)
+            }
+        };
+        let implied: &mut Vec<String> = features.entry(name.clone()).or_default();
+        implied.extend(values.split('"').skip(1).step_by(2).map(str::to_owned));
+        if !values.contains(']') {
+            current = Some(name);
+        }
+    }
+    features
+}
+
+// Enables `feature` and the features it implies.
+fn enable(features: &BTreeMap<String, Vec<String>>, feature: &str) {
+    let implied = features
+        .get(feature)
+        .unwrap_or_else(|| panic!("`{}` is not a feature of ring-buffer-smoketest", feature));
+    println!("cargo::rustc-cfg=feature=\"{}\"", feature);
+    for implied in implied {
+        enable(features, implied);
+    }
+}
+
+fn main() {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("../../../Cargo.toml");
+    println!("cargo::rerun-if-changed={}", root.display());
+    let features = parse_features(&fs::read_to_string(&root).unwrap());
+
+    let values: Vec<String> = features.keys().map(|name| format!("\"{}\"", name)).collect();
+    println!("cargo::rustc-check-cfg=cfg(feature, values({}))", values.join(", "));
+
+    let package = env::var("CARGO_PKG_NAME").unwrap();
+    let (_, enabled) = FEATURES
+        .iter()
+        .find(|(name, _)| *name == package)
+        .unwrap_or_else(|| panic!("no features for {}", package));
+    for feature in *enabled {
+        enable(&features, feature);
+    }
+}
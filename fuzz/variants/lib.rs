@@ -0,0 +1,13 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The collections of `ring-buffer-smoketest`, built as a crate of their own.
+//!
+//! Cargo unifies the features of a crate within a build, so the fuzz target
+//! can't link the ring buffer both with and without `assume-invariants`.
+//! Instead, `ring-buffer-assumed` and `ring-buffer-unassumed` both build this
+//! file, each with the features that `build.rs` enables.
+#![no_std]
+
+#[path = "../../src/collections/mod.rs"]
+pub mod collections;
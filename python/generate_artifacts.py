@@ -2,13 +2,19 @@
 # pyright: reportMissingTypeStubs=false
 
 """
-This script compiles two Rust projects (one with assertions enabled and one without),
+This script compiles the Rust project once per invariant mode (see `VARIANTS`,
+which can be extended with `--variant` to build any subset of the `assume-*`
+features),
 extracts function sizes for ring buffer related functions, and outputs:
 - The total ELF size for the test harness
-- The size of each function in both builds
-- The disassembly of each function in both builds
-
-This compiles the projects on three architectures: x86, riscv32, and arm32.
+- The size of each function in each build
+- The disassembly of each function in each build
+- Whether the functions in `PANIC_FREE_FUNCTIONS` call into the panic machinery
+- The size of each ring buffer struct (see `SIZE_OF_*` in `src/main.rs`)
+- The size and panic-freedom of the functions in `RING_INDEX_FUNCTIONS` with
+  `ring-index` and with `assume-invariants`
+
+This compiles every build on three architectures: x86, riscv32, and arm32.
 In `rustup` world, these are `i686-unknown-linux-gnu`, `riscv32i-unknown-none-elf`,
 and `armv7-unknown-linux-gnueabihf`.
 
@@ -20,14 +26,37 @@ for Ubuntu:
 
 See this repository's Dockerfile for an example of how to set this up.
 """
+import argparse
 import subprocess
 import re
 import os
 import csv
 from typing import Optional, List, Dict
 
-WITH_ASSERTIONS_DIR = "with_assertions"
-WITHOUT_ASSERTIONS_DIR = "without_assertions"
+PROJECT_DIR = "."
+# All builds come from the same crate and only differ in their features. Each
+# one gets its own target directory so that they don't overwrite each other.
+# Sizes are compared against `BASELINE_VARIANT`, which tells the compiler nothing.
+VARIANTS = {
+    "with": {
+        "target_dir": "target/with_assertions",
+        "features": ["assume-invariants"],
+    },
+    "without": {
+        "target_dir": "target/without_assertions",
+        "features": [],
+    },
+    "checked": {
+        "target_dir": "target/checked_assertions",
+        "features": ["check-invariants"],
+    },
+    # The safe alternative to `with` for `enqueue`, `push` and `dequeue`.
+    "index": {
+        "target_dir": "target/ring_index",
+        "features": ["ring-index"],
+    },
+}
+BASELINE_VARIANT = "without"
 BINARY_NAME = "ring-buffer-smoketest"
 OUT_DIR = "out"
 DISASM_OUT_DIR = os.path.join(OUT_DIR, "disasm")
@@ -44,8 +73,63 @@ EXPECTED_FUNCTIONS = [
     "remove_first_matching",
     "empty",
     "retain",
+    "retain_mut",
+    "position",
+    "remove",
+    "insert",
+    "swap_remove_back",
+    "swap_remove_front",
+    "remove_first_matching_unordered",
+    "truncate",
+    "truncate_front",
+    "split_off_into",
+    "migrate",
+    "reclaim",
+    "validate",
+    "try_from_parts",
+    "extract_if",
+    "requeue",
+]
+
+# The owned ring buffers with compact indices, whose methods are compared with
+# the `RingBuffer` ones.
+ARRAY_RING_BUFFERS = ["ArrayRingBuffer8", "ArrayRingBuffer16"]
+ARRAY_RING_BUFFER_METHODS = [
+    "available_len",
+    "as_slices",
+    "has_elements",
+    "is_full",
+    "len",
+    "enqueue",
+    "push",
+    "dequeue",
+    "remove_first_matching",
+    "empty",
+    "retain",
+    "retain_mut",
+    "position",
+]
+EXPECTED_FUNCTIONS += [
+    f"{ty}::{method}" for ty in ARRAY_RING_BUFFERS for method in ARRAY_RING_BUFFER_METHODS
+]
+
+# Harness functions that use the typestate guards, which should not be able to
+# panic in the builds that assume the invariants (see `expected_panic_free` in
+# `panic_free.csv`): the other builds keep their bounds checks. These inline
+# everything they call, so looking for direct calls to a panic function is
+# enough.
+PANIC_FREE_FUNCTIONS = [
+    "call_non_empty_front",
+    "call_non_empty_dequeue",
+    "call_not_full_enqueue",
 ]
 
+# The functions that access the ends of the ring, which `ring-index` (the
+# `index` build) and `assume-invariants` (the `with` build) each try to free
+# from bounds checks. `ring_index.csv` compares them, against `without`.
+RING_INDEX_FUNCTIONS = ["enqueue", "push", "dequeue"] + PANIC_FREE_FUNCTIONS
+RING_INDEX_VARIANTS = ["index", "with", "without"]
+
 ARCHITECTURES = {
     "x86": {
         "target": "i686-unknown-linux-gnu",
@@ -63,6 +147,8 @@ def simple_fn_name(full_name: str) -> str:
     Extracts the last function/method name from a Rust demangled symbol.
     E.g.,
     '<<RingBuffer<T> as Queue<T>>::has_elements>' -> 'has_elements'
+    The methods of the `ARRAY_RING_BUFFERS` are prefixed with their type, e.g.
+    'ArrayRingBuffer8::has_elements', so that they don't collide with these.
     """
     # Remove leading/trailing angle brackets
     name = full_name.strip("<>")
@@ -71,7 +157,8 @@ def simple_fn_name(full_name: str) -> str:
     # assert "::" in name, f"Expected '::' in demangled function name, got: {name}"
     parts = name.split("::")
     if parts:
-        return parts[-1]
+        owner = re.search(r'::(' + '|'.join(ARRAY_RING_BUFFERS) + r')<', full_name)
+        return f"{owner.group(1)}::{parts[-1]}" if owner else parts[-1]
     else:
         return name
 
@@ -104,10 +191,11 @@ def clean_project(project_path: str):
         print(f"[cargo clean] failed for {project_path}:\n{clean_proc.stderr}")
         raise RuntimeError(f"cargo clean failed for {project_path}")
 
-def compile_project(project_path: str, arch: str):
+def compile_project(project_path: str, variant: Dict[str, object], arch: str):
     # Build step
     env = os.environ.copy()
     env["RUSTFLAGS"] = "-C link-arg=-nostdlib"
+    features = ",".join(variant["features"])
     build_proc = subprocess.run(
         [
             "cargo",
@@ -115,6 +203,10 @@ def compile_project(project_path: str, arch: str):
             "--release",
             "--target",
             arch,
+            "--target-dir",
+            variant["target_dir"],
+            "--features",
+            features,
         ],
         cwd=project_path,
         env=env,
@@ -124,8 +216,8 @@ def compile_project(project_path: str, arch: str):
         text=True,
     )
     if build_proc.returncode != 0:
-        print(f"[cargo build] failed for {project_path} ({arch}):\n{build_proc.stderr}")
-        raise RuntimeError(f"cargo build failed for {project_path} ({arch})")
+        print(f"[cargo build] failed for {project_path} ({arch}, features: [{features}]):\n{build_proc.stderr}")
+        raise RuntimeError(f"cargo build failed for {project_path} ({arch}, features: [{features}])")
 
 def add_function_entry(functions: Dict[str, Dict[str, object]], current_fn: Optional[str], start_addr: Optional[int], last_addr: Optional[int], asm_lines: List[str]):
     if current_fn is not None and start_addr is not None and last_addr is not None:
@@ -182,71 +274,163 @@ def get_functions_with_asm(binary: str) -> Dict[str, Dict[str, object]]:
     return functions
 
 
+def calls_panic(asm: str) -> bool:
+    """
+    Returns whether the disassembly contains a direct call to a panic function,
+    e.g. `call 2e79 <_RNvNtCs..._4core9panicking18panic_bounds_check>`.
+    """
+    return re.search(r'<[^>]*panic[^>]*>', asm) is not None
+
+
+def get_struct_sizes(binary: str) -> Dict[str, int]:
+    """
+    Reads the `SIZE_OF_*` symbols of the harness, whose sizes are the sizes of
+    the ring buffer structs, e.g. `{"RING_BUFFER": 16}`.
+    """
+    result = subprocess.run(["llvm-nm", "--print-size", binary],
+                            capture_output=True, text=True)
+    if result.returncode != 0:
+        print("llvm-nm failed:", result.stderr)
+        return {}
+
+    sizes: Dict[str, int] = {}
+    for line in result.stdout.splitlines():
+        # "000003a4 00000044 R SIZE_OF_ARRAY_RING_BUFFER8"
+        m = re.match(r'^[0-9a-f]+ ([0-9a-f]+) \w SIZE_OF_(\w+)$', line)
+        if m:
+            sizes[m.group(2)] = int(m.group(1), 16)
+    return sizes
+
+
+def parse_args():
+    parser = argparse.ArgumentParser(description="Compare the code size of the invariant modes.")
+    parser.add_argument(
+        "--variant",
+        action="append",
+        default=[],
+        metavar="NAME=FEATURES",
+        help="also build with the given comma-separated features, "
+        "e.g. `head=assume-head-in-bounds` (can be repeated)",
+    )
+    return parser.parse_args()
+
 
 if __name__ == "__main__":
+    args = parse_args()
+    for spec in args.variant:
+        name, _, features = spec.partition("=")
+        assert name and name not in VARIANTS, f"Invalid or duplicate variant name: {spec}"
+        VARIANTS[name] = {
+            "target_dir": f"target/variant_{name}",
+            "features": [f for f in features.split(",") if f],
+        }
+
     # "cd" to the ROOT_DIR in the environment.
     assert "ROOT_DIR" in os.environ, "ROOT_DIR environment variable not set"
 
     os.chdir(os.environ["ROOT_DIR"])
 
-    # Clean both projects once at the start
-    clean_project(WITH_ASSERTIONS_DIR)
-    clean_project(WITHOUT_ASSERTIONS_DIR)
+    # Clean the project once at the start
+    clean_project(PROJECT_DIR)
 
     # Prepare CSV writers
     os.makedirs(OUT_DIR, exist_ok=True)
     per_function_csv = os.path.join(OUT_DIR, "function_sizes.csv")
     elf_size_csv = os.path.join(OUT_DIR, "elf_sizes.csv")
+    panic_free_csv = os.path.join(OUT_DIR, "panic_free.csv")
+    struct_size_csv = os.path.join(OUT_DIR, "struct_sizes.csv")
+    ring_index_csv = os.path.join(OUT_DIR, "ring_index.csv")
 
-    with open(per_function_csv, "w", newline="") as func_csv, open(elf_size_csv, "w", newline="") as elf_csv:
+    with open(per_function_csv, "w", newline="") as func_csv, open(elf_size_csv, "w", newline="") as elf_csv, open(panic_free_csv, "w", newline="") as panic_csv, open(struct_size_csv, "w", newline="") as struct_csv, open(ring_index_csv, "w", newline="") as index_csv:
         func_writer = csv.writer(func_csv)
         elf_writer = csv.writer(elf_csv)
+        panic_writer = csv.writer(panic_csv)
+        panic_writer.writerow(
+            ["function", "arch"] + [f"panic_free_{name}" for name in VARIANTS] + ["expected_panic_free"]
+        )
+        expected_panic_free = " ".join(
+            name for name, variant in VARIANTS.items() if "assume-invariants" in variant["features"]
+        )
+        struct_writer = csv.writer(struct_csv)
+        struct_writer.writerow(["struct", "arch", "size"])
+        index_writer = csv.writer(index_csv)
+        index_writer.writerow(
+            ["function", "arch"]
+            + [f"size_{name}" for name in RING_INDEX_VARIANTS]
+            + [f"panic_free_{name}" for name in RING_INDEX_VARIANTS]
+        )
 
 
         all_func_rows = []
 
+        compared_variants = [v for v in VARIANTS if v != BASELINE_VARIANT]
+
         for arch_name, arch_info in ARCHITECTURES.items():
             print(f"Compiling for architecture: {arch_name}")
-            compile_project(WITH_ASSERTIONS_DIR, arch_info["target"])
-            compile_project(WITHOUT_ASSERTIONS_DIR, arch_info["target"])
+            for variant in VARIANTS.values():
+                compile_project(PROJECT_DIR, variant, arch_info["target"])
             print(f"Done compiling for {arch_name}!")
 
+            bins = {
+                name: f"{variant['target_dir']}/{arch_info['target']}/release/{BINARY_NAME}"
+                for name, variant in VARIANTS.items()
+            }
+
             # Get ELF sizes
-            with_bin = f"{WITH_ASSERTIONS_DIR}/target/{arch_info['target']}/release/{BINARY_NAME}"
-            without_bin = f"{WITHOUT_ASSERTIONS_DIR}/target/{arch_info['target']}/release/{BINARY_NAME}"
+            elf_sizes = []
+            for name in VARIANTS:
+                try:
+                    elf_sizes.append(os.path.getsize(bins[name]))
+                except Exception:
+                    elf_sizes.append(0)
 
-            # Get file sizes
-            try:
-                total_elf_with = os.path.getsize(with_bin)
-            except Exception:
-                total_elf_with = 0
-            try:
-                total_elf_without = os.path.getsize(without_bin)
-            except Exception:
-                total_elf_without = 0
+            elf_writer.writerow([arch_name] + elf_sizes)
 
-            elf_writer.writerow([arch_name, total_elf_with, total_elf_without])
+            # Struct sizes don't depend on the features.
+            for struct, size in sorted(get_struct_sizes(bins[BASELINE_VARIANT]).items()):
+                struct_writer.writerow([struct, arch_name, size])
 
             # Get per-function sizes
-            with_sizes = get_functions_with_asm(with_bin)
-            without_sizes = get_functions_with_asm(without_bin)
-            all_funcs = set(with_sizes.keys()) | set(without_sizes.keys())
+            sizes = {name: get_functions_with_asm(bins[name]) for name in VARIANTS}
+            all_funcs = set().union(*(v.keys() for v in sizes.values()))
+
+            # The linker may fold identical functions together, in which case
+            # only one of them has a symbol.
+            for fn in PANIC_FREE_FUNCTIONS:
+                panic_writer.writerow([fn, arch_name] + [
+                    ("no" if calls_panic(sizes[name][fn]["asm"]) else "yes") if fn in sizes[name] else "folded"
+                    for name in VARIANTS
+                ] + [expected_panic_free])
+
+            for fn in RING_INDEX_FUNCTIONS:
+                found = {name: sizes[name][fn] for name in RING_INDEX_VARIANTS if fn in sizes[name]}
+                index_writer.writerow(
+                    [fn, arch_name]
+                    + [found[name]["size"] if name in found else "folded" for name in RING_INDEX_VARIANTS]
+                    + [
+                        ("no" if calls_panic(found[name]["asm"]) else "yes") if name in found else "folded"
+                        for name in RING_INDEX_VARIANTS
+                    ]
+                )
+                if "index" in found and "with" in found:
+                    print(
+                        f"{arch_name}: {fn} is {found['index']['size']} bytes with ring-index, "
+                        f"{found['with']['size']} with assume-invariants"
+                    )
 
             for fn in all_funcs:
-                size_with = with_sizes.get(fn, {}).get("size", 0)
-                size_without = without_sizes.get(fn, {}).get("size", 0)
-                delta = size_with - size_without
-                all_func_rows.append([fn, arch_name, size_with, size_without, delta])
-
-                for which, sizes in [("with", with_sizes), ("without", without_sizes)]:
-                    if fn in sizes:
-                        asm = sizes[fn].get("asm", "")
+                fn_sizes = {name: sizes[name].get(fn, {}).get("size", 0) for name in VARIANTS}
+                deltas = [fn_sizes[v] - fn_sizes[BASELINE_VARIANT] for v in compared_variants]
+                all_func_rows.append([fn, arch_name] + [fn_sizes[name] for name in VARIANTS] + deltas)
+
+                for which, variant_sizes in sizes.items():
+                    if fn in variant_sizes:
+                        asm = variant_sizes[fn].get("asm", "")
                         if asm:
                             # Directory: out/disasm/{function}
                             func_dir = os.path.join(DISASM_OUT_DIR, fn)
                             os.makedirs(func_dir, exist_ok=True)
-                            # File: out/disasm/{function}/{arch}-{function}.asm
-                            # Optionally, add -with or -without to the filename for clarity
+                            # File: out/disasm/{function}/{arch}-{function}-{variant}.asm
                             filename = f"{arch_name}-{fn}-{which}.asm"
                             file_path = os.path.join(func_dir, filename)
                             with open(file_path, "w") as f:
@@ -256,8 +440,12 @@ if __name__ == "__main__":
         all_func_rows.sort(key=lambda row: (row[0], row[1]))
 
         # Write header
-        elf_writer.writerow(["arch", "total_elf_with", "total_elf_without"])
-        func_writer.writerow(["function", "arch", "size_with", "size_without", "delta"])
+        elf_writer.writerow(["arch"] + [f"total_elf_{name}" for name in VARIANTS])
+        func_writer.writerow(
+            ["function", "arch"]
+            + [f"size_{name}" for name in VARIANTS]
+            + [f"delta_{name}" for name in compared_variants]
+        )
 
         # Write sorted rows
         for row in all_func_rows:
@@ -265,17 +453,21 @@ if __name__ == "__main__":
 
         # Append summary rows
         func_writer.writerow([])  # Blank line for separation
-        func_writer.writerow(["arch", "total_diff", "percent_change"])
+        func_writer.writerow(["arch", "variant", "total_diff", "percent_change"])
 
+        names = list(VARIANTS)
+        baseline_col = 2 + names.index(BASELINE_VARIANT)
         for arch_name in ARCHITECTURES.keys():
             # Only consider EXPECTED_FUNCTIONS
             arch_rows = [row for row in all_func_rows if row[1] == arch_name and row[0] in EXPECTED_FUNCTIONS]
-            total_with = sum(row[2] for row in arch_rows)
-            total_without = sum(row[3] for row in arch_rows)
-            total_diff = total_with - total_without
-            percent_change = (total_diff / total_without * 100) if total_without != 0 else 0
-            func_writer.writerow([
-                arch_name,
-                total_diff,
-                f"{percent_change:.2f}%"
-            ])
+            total_baseline = sum(row[baseline_col] for row in arch_rows)
+            for name in compared_variants:
+                total = sum(row[2 + names.index(name)] for row in arch_rows)
+                total_diff = total - total_baseline
+                percent_change = (total_diff / total_baseline * 100) if total_baseline != 0 else 0
+                func_writer.writerow([
+                    arch_name,
+                    name,
+                    total_diff,
+                    f"{percent_change:.2f}%"
+                ])
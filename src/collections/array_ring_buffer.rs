@@ -0,0 +1,418 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Ring buffers that own their storage, with compact indices.
+//!
+//! [`RingBuffer`](super::ring_buffer::RingBuffer) borrows its ring as a slice
+//! and stores `usize` indices, so it takes four words besides the ring. Small
+//! queues can instead own a `[T; N]` array and use `u8` ([`ArrayRingBuffer8`],
+//! for `N <= 256`) or `u16` ([`ArrayRingBuffer16`], for `N <= 65536`) head and
+//! tail, which makes the struct smaller, and index the array with them
+//! directly.
+//!
+//! Both types are generated by the same macro, and otherwise behave like
+//! `RingBuffer`: a ring of `N` slots holds at most `N - 1` elements.
+
+use crate::collections::queue;
+use derive_assumptions::derive_assumptions;
+use core::marker::Copy;
+use core::ops::FnMut;
+use core::option::{Option, Option::{None, Some}};
+
+macro_rules! array_ring_buffer {
+    ($(#[$doc:meta])* $name:ident, $index:ident) => {
+        $(#[$doc])*
+        #[derive_assumptions(features = [
+            "assume-ring-len",
+            "assume-head-in-bounds",
+            "assume-tail-in-bounds",
+        ])]
+        #[flux_rs::refined_by(hd: int, tl: int)]
+        #[flux_rs::invariant(N > 1)]
+        #[flux_rs::invariant(hd < N)]
+        #[flux_rs::invariant(tl < N)]
+        pub struct $name<T, const N: usize> {
+            ring: [T; N],
+            #[field({$index[hd] | hd < N})]
+            head: $index,
+            #[field({$index[tl] | tl < N})]
+            tail: $index,
+        }
+
+        impl<T: Copy, const N: usize> $name<T, N> {
+            /// Creates an empty ring buffer over `ring`.
+            ///
+            /// Fails to compile unless `1 < N` and every index of `ring` fits
+            /// in the index type.
+            #[flux_rs::sig(fn([T; N]) -> $name<T, N>[0, 0] requires N > 1)]
+            #[inline(never)]
+            pub fn new(ring: [T; N]) -> Self {
+                const {
+                    assert!(
+                        N > 1 && N - 1 <= $index::MAX as usize,
+                        concat!("invalid length for a ", stringify!($name))
+                    )
+                };
+                $name {
+                    ring,
+                    head: 0,
+                    tail: 0,
+                }
+            }
+
+            // The index after `index`, wrapping around at the end of the ring.
+            #[flux_rs::sig(fn($index{i: i < N}) -> $index{j: j == next_index(i, N)})]
+            #[inline(always)]
+            fn next(index: $index) -> $index {
+                ((index as usize + 1) % N) as $index
+            }
+
+            /// Returns the number of elements that can be enqueued until the
+            /// ring buffer is full.
+            #[flux_rs::sig(fn(&$name<T, N>[@rb]) -> usize[N - 1 - dist(rb.hd, rb.tl, N)])]
+            #[inline(never)]
+            pub fn available_len(&self) -> usize {
+                assert_invariants!(self);
+                N - 1 - queue::Queue::len(self)
+            }
+
+            /// Returns the contents of the ring buffer as at most two slices,
+            /// like [`RingBuffer::as_slices`](super::ring_buffer::RingBuffer::as_slices).
+            #[inline(never)]
+            pub fn as_slices(&self) -> (Option<&[T]>, Option<&[T]>) {
+                assert_invariants!(self);
+                let head = self.head as usize;
+                let tail = self.tail as usize;
+                if head < tail {
+                    (Some(&self.ring[head..tail]), None)
+                } else if head > tail {
+                    let (left, right) = self.ring.split_at(head);
+                    (
+                        Some(right),
+                        if tail == 0 {
+                            None
+                        } else {
+                            Some(&left[..tail])
+                        },
+                    )
+                } else {
+                    (None, None)
+                }
+            }
+        }
+
+        #[flux_rs::assoc(fn length(rb: $name) -> int { dist(rb.hd, rb.tl, N) })]
+        #[flux_rs::assoc(fn capacity(rb: $name) -> int { N - 1 })]
+        impl<T: Copy, const N: usize> queue::Queue<T> for $name<T, N> {
+            #[flux_rs::sig(fn(&$name<T, N>[@rb]) -> bool[rb.hd != rb.tl])]
+            #[inline(never)]
+            fn has_elements(&self) -> bool {
+                assert_invariants!(self);
+                self.head != self.tail
+            }
+
+            #[flux_rs::sig(fn(&$name<T, N>[@rb]) -> bool[rb.hd == next_index(rb.tl, N)])]
+            #[inline(never)]
+            fn is_full(&self) -> bool {
+                assert_invariants!(self);
+                self.head == Self::next(self.tail)
+            }
+
+            #[flux_rs::sig(fn(&$name<T, N>[@rb]) -> usize[dist(rb.hd, rb.tl, N)])]
+            #[inline(never)]
+            fn len(&self) -> usize {
+                assert_invariants!(self);
+                let head = self.head as usize;
+                let tail = self.tail as usize;
+                if tail >= head {
+                    tail - head
+                } else {
+                    (N - head) + tail
+                }
+            }
+
+            #[flux_rs::sig(fn(&$name<T, N>[@rb]) -> usize[N - 1 - dist(rb.hd, rb.tl, N)])]
+            #[inline(always)]
+            fn available_len(&self) -> usize {
+                $name::available_len(self)
+            }
+
+            #[flux_rs::sig(fn(&$name<T, N>) -> usize[N - 1])]
+            #[inline(always)]
+            fn capacity(&self) -> usize {
+                N - 1
+            }
+
+            #[flux_rs::sig(
+                fn(self: &strg $name<T, N>[@old], _) -> bool[old.hd != next_index(old.tl, N)]
+                    ensures self: $name<T, N>{ new:
+                        new.hd == old.hd
+                        &&
+                        (old.hd == next_index(old.tl, N) => new.tl == old.tl)
+                        &&
+                        (old.hd != next_index(old.tl, N) => new.tl == next_index(old.tl, N))
+                    }
+            )]
+            #[inline(never)]
+            fn enqueue(&mut self, val: T) -> bool {
+                assert_invariants!(self);
+                if self.is_full() {
+                    false
+                } else {
+                    self.ring[self.tail as usize] = val;
+                    self.tail = Self::next(self.tail);
+                    true
+                }
+            }
+
+            #[flux_rs::sig(
+                fn(self: &strg $name<T, N>[@old], _) -> Option<T>[old.hd == next_index(old.tl, N)]
+                    ensures self: $name<T, N>{ new:
+                        new.tl == next_index(old.tl, N)
+                        &&
+                        (old.hd == next_index(old.tl, N) => new.hd == next_index(old.hd, N))
+                        &&
+                        (old.hd != next_index(old.tl, N) => new.hd == old.hd)
+                    }
+            )]
+            #[inline(never)]
+            fn push(&mut self, val: T) -> Option<T> {
+                assert_invariants!(self);
+                let result = if self.is_full() {
+                    let val = self.ring[self.head as usize];
+                    self.head = Self::next(self.head);
+                    Some(val)
+                } else {
+                    None
+                };
+
+                self.ring[self.tail as usize] = val;
+                self.tail = Self::next(self.tail);
+                result
+            }
+
+            #[flux_rs::sig(
+                fn(self: &strg $name<T, N>[@old]) -> Option<T>[old.hd != old.tl]
+                    ensures self: $name<T, N>{ new:
+                        new.tl == old.tl
+                        &&
+                        (old.hd == old.tl => new.hd == old.hd)
+                        &&
+                        (old.hd != old.tl => new.hd == next_index(old.hd, N))
+                    }
+            )]
+            #[inline(never)]
+            fn dequeue(&mut self) -> Option<T> {
+                assert_invariants!(self);
+                if self.has_elements() {
+                    let val = self.ring[self.head as usize];
+                    self.head = Self::next(self.head);
+                    Some(val)
+                } else {
+                    None
+                }
+            }
+
+            #[flux_rs::sig(
+                fn(self: &strg $name<T, N>[@old], _) -> Option<T>[@removed]
+                    ensures self: $name<T, N>{ new:
+                        new.hd == old.hd
+                        &&
+                        (removed => dist(new.hd, new.tl, N) == dist(old.hd, old.tl, N) - 1)
+                        &&
+                        (!removed => new == old)
+                    }
+            )]
+            #[flux_rs::qualifiers(SrcInRange, NextSlot)]
+            #[inline(never)]
+            fn remove_first_matching<F>(&mut self, mut f: F) -> Option<T>
+            where
+                F: FnMut(&T) -> bool,
+            {
+                assert_invariants!(self);
+                let mut slot = self.head;
+                while slot != self.tail {
+                    if f(&self.ring[slot as usize]) {
+                        let val = self.ring[slot as usize];
+
+                        let mut next_slot = Self::next(slot);
+                        // Move everything past this element forward in the ring
+                        while next_slot != self.tail {
+                            self.ring[slot as usize] = self.ring[next_slot as usize];
+                            slot = next_slot;
+                            next_slot = Self::next(next_slot);
+                        }
+                        self.tail = slot;
+                        return Some(val);
+                    }
+                    slot = Self::next(slot);
+                }
+                None
+            }
+
+            #[flux_rs::sig(fn(self: &strg $name<T, N>[@old]) ensures self: $name<T, N>[0, 0])]
+            #[inline(never)]
+            fn empty(&mut self) {
+                assert_invariants!(self);
+                self.head = 0;
+                self.tail = 0;
+            }
+
+            #[flux_rs::sig(
+                fn(self: &strg $name<T, N>[@old], _) -> usize[@removed]
+                    ensures self: $name<T, N>{ new:
+                        new.hd == old.hd && dist(new.hd, new.tl, N) == dist(old.hd, old.tl, N) - removed
+                    }
+            )]
+            #[inline(never)]
+            fn retain<F>(&mut self, mut f: F) -> usize
+            where
+                F: FnMut(&T) -> bool,
+            {
+                self.retain_mut(|elem| f(elem))
+            }
+
+            #[flux_rs::sig(
+                fn(self: &strg $name<T, N>[@old], _) -> usize[@removed]
+                    ensures self: $name<T, N>{ new:
+                        new.hd == old.hd && dist(new.hd, new.tl, N) == dist(old.hd, old.tl, N) - removed
+                    }
+            )]
+            #[flux_rs::qualifiers(SrcInRange, DstTrailsSrc, RemovedCount)]
+            #[inline(never)]
+            fn retain_mut<F>(&mut self, mut f: F) -> usize
+            where
+                F: FnMut(&mut T) -> bool,
+            {
+                assert_invariants!(self);
+                // Index over the elements before the retain operation.
+                let mut src = self.head;
+                // Index over the retained elements.
+                let mut dst = self.head;
+                let mut removed = 0;
+
+                while src != self.tail {
+                    if f(&mut self.ring[src as usize]) {
+                        if src != dst {
+                            // `dst` trails `src`, see `RingBuffer::retain_mut`.
+                            let dst = dst as usize;
+                            assert_dst_in_bounds!(dst, N);
+                            self.ring[dst] = self.ring[src as usize];
+                        }
+                        dst = Self::next(dst);
+                    } else {
+                        removed += 1;
+                    }
+                    src = Self::next(src);
+                }
+
+                self.tail = dst;
+                removed
+            }
+
+            #[flux_rs::sig(fn(&$name<T, N>[@rb], _) -> Option<usize{i: i < dist(rb.hd, rb.tl, N)}>)]
+            #[flux_rs::qualifiers(SrcInRange, IndexOfSlot)]
+            #[inline(never)]
+            fn position<F>(&self, mut f: F) -> Option<usize>
+            where
+                F: FnMut(&T) -> bool,
+            {
+                assert_invariants!(self);
+                let mut slot = self.head;
+                let mut index = 0;
+                while slot != self.tail {
+                    if f(&self.ring[slot as usize]) {
+                        return Some(index);
+                    }
+                    slot = Self::next(slot);
+                    index += 1;
+                }
+                None
+            }
+        }
+    };
+}
+
+array_ring_buffer!(
+    /// A ring buffer over an owned array of `N <= 256` slots, with `u8`
+    /// indices.
+    ArrayRingBuffer8,
+    u8
+);
+
+array_ring_buffer!(
+    /// A ring buffer over an owned array of `N <= 65536` slots, with `u16`
+    /// indices.
+    ArrayRingBuffer16,
+    u16
+);
+
+#[cfg(test)]
+mod test {
+    use super::super::queue::Queue;
+    use super::super::ring_buffer::RingBuffer;
+    use super::{ArrayRingBuffer16, ArrayRingBuffer8};
+    use core::mem::size_of;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(size_of::<ArrayRingBuffer8<u8, 16>>(), 18);
+        assert_eq!(size_of::<ArrayRingBuffer16<u16, 16>>(), 36);
+        assert!(size_of::<ArrayRingBuffer8<u8, 16>>() < size_of::<RingBuffer<u8>>() + 16);
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        const LEN: usize = 256;
+        let mut buf = ArrayRingBuffer8::new([0; LEN]);
+        // Push enough elements to wrap around the `u8` indices a few times.
+        for i in 0..4 * LEN {
+            assert_eq!(buf.push(i), if i < LEN - 1 { None } else { Some(i + 1 - LEN) });
+            assert_eq!(buf.len(), (i + 1).min(LEN - 1));
+        }
+        assert!(buf.is_full());
+        assert_eq!(buf.available_len(), 0);
+
+        let (front, back) = buf.as_slices();
+        let total = front.map_or(0, |s| s.len()) + back.map_or(0, |s| s.len());
+        assert_eq!(total, LEN - 1);
+
+        for i in 3 * LEN + 1..4 * LEN {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert!(!buf.has_elements());
+    }
+
+    #[test]
+    fn test_queue_operations() {
+        let mut buf = ArrayRingBuffer16::new([0; 300]);
+        for i in 0..200 {
+            assert!(buf.enqueue(i));
+        }
+        for i in 0..150 {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        // The tail wraps around.
+        for i in 200..400 {
+            assert!(buf.enqueue(i));
+        }
+        assert_eq!(buf.len(), 250);
+        assert_eq!(buf.remove_first_matching(|&x| x == 299), Some(299));
+        assert_eq!(buf.position(|&x| x == 300), Some(149));
+        assert_eq!(buf.retain(|&x| x % 2 == 0), 124);
+        assert_eq!(buf.retain_mut(|x| {
+            *x += 1;
+            *x % 4 == 1
+        }), 63);
+        assert_eq!(buf.len(), 62);
+        for i in 0..62 {
+            assert_eq!(buf.dequeue(), Some(152 + 4 * i + 1));
+        }
+        buf.empty();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(buf.available_len(), 299);
+    }
+
+    crate::queue_conformance_tests!(conformance8, || ArrayRingBuffer8::new([0u32; 10]));
+    crate::queue_conformance_tests!(conformance16, || ArrayRingBuffer16::new([0i64; 300]));
+}
@@ -0,0 +1,333 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A `queue::Queue` wrapper for debugging, which checks the consistency of the
+//! wrapped queue after every call.
+//!
+//! [`CheckedQueue`] forwards every method to the queue it wraps, then checks
+//! that:
+//! - `len() + available_len()` is `capacity()`;
+//! - `has_elements()` is `len() > 0`;
+//! - `is_full()` is `available_len() == 0`.
+//!
+//! When one doesn't hold, it panics with the method that broke it and the last
+//! [`TRACE_LEN`] calls that modified the queue, oldest first.
+
+use crate::collections::queue::Queue;
+use core::fmt;
+use core::ops::FnMut;
+use core::option::Option;
+
+/// How many calls [`CheckedQueue`] remembers.
+pub const TRACE_LEN: usize = 8;
+
+/// A call that modified the queue, and the length of the queue after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub op: &'static str,
+    pub len: usize,
+}
+
+/// The last [`TRACE_LEN`] calls that modified a queue.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    calls: [Call; TRACE_LEN],
+    // The index of the next call to record, and how many are recorded.
+    next: usize,
+    count: usize,
+}
+
+impl Trace {
+    const fn new() -> Self {
+        Trace {
+            calls: [Call { op: "", len: 0 }; TRACE_LEN],
+            next: 0,
+            count: 0,
+        }
+    }
+
+    fn record(&mut self, op: &'static str, len: usize) {
+        self.calls[self.next] = Call { op, len };
+        self.next = (self.next + 1) % TRACE_LEN;
+        if self.count < TRACE_LEN {
+            self.count += 1;
+        }
+    }
+
+    /// Returns the recorded calls, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Call> {
+        let start = (self.next + TRACE_LEN - self.count) % TRACE_LEN;
+        (0..self.count).map(move |i| &self.calls[(start + i) % TRACE_LEN])
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, call) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} (len {})", call.op, call.len)?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Wraps a queue, and checks its consistency after every call.
+pub struct CheckedQueue<Q> {
+    queue: Q,
+    trace: Trace,
+}
+
+#[flux_rs::ignore]
+impl<Q> CheckedQueue<Q> {
+    pub fn new(queue: Q) -> Self {
+        CheckedQueue {
+            queue,
+            trace: Trace::new(),
+        }
+    }
+
+    pub fn inner(&self) -> &Q {
+        &self.queue
+    }
+
+    pub fn into_inner(self) -> Q {
+        self.queue
+    }
+
+    /// Returns the last calls that modified the queue.
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    // Checks the queue after `op`.
+    fn check<T>(&self, op: &str)
+    where
+        Q: Queue<T>,
+    {
+        let len = self.queue.len();
+        let available_len = self.queue.available_len();
+        let capacity = self.queue.capacity();
+        let has_elements = self.queue.has_elements();
+        let is_full = self.queue.is_full();
+        assert!(
+            len + available_len == capacity,
+            "{} broke the queue: len {} + available_len {} != capacity {}, after {}",
+            op,
+            len,
+            available_len,
+            capacity,
+            self.trace
+        );
+        assert!(
+            has_elements == (len > 0),
+            "{} broke the queue: has_elements is {} with len {}, after {}",
+            op,
+            has_elements,
+            len,
+            self.trace
+        );
+        assert!(
+            is_full == (available_len == 0),
+            "{} broke the queue: is_full is {} with available_len {}, after {}",
+            op,
+            is_full,
+            available_len,
+            self.trace
+        );
+    }
+}
+
+// Calls `$method` on the queue, and checks it, without recording the call.
+macro_rules! inspect {
+    ($self:ident . $method:ident($($arg:expr),*)) => {{
+        let result = $self.queue.$method($($arg),*);
+        $self.check(stringify!($method));
+        result
+    }};
+}
+
+// Calls `$method` on the queue, records the call, and checks the queue.
+macro_rules! modify {
+    ($self:ident . $method:ident($($arg:expr),*)) => {{
+        let result = $self.queue.$method($($arg),*);
+        $self.trace.record(stringify!($method), $self.queue.len());
+        $self.check(stringify!($method));
+        result
+    }};
+}
+
+#[flux_rs::ignore]
+impl<T, Q: Queue<T>> Queue<T> for CheckedQueue<Q> {
+    fn has_elements(&self) -> bool {
+        inspect!(self.has_elements())
+    }
+
+    fn is_full(&self) -> bool {
+        inspect!(self.is_full())
+    }
+
+    fn len(&self) -> usize {
+        inspect!(self.len())
+    }
+
+    fn available_len(&self) -> usize {
+        inspect!(self.available_len())
+    }
+
+    fn capacity(&self) -> usize {
+        inspect!(self.capacity())
+    }
+
+    fn enqueue(&mut self, val: T) -> bool {
+        modify!(self.enqueue(val))
+    }
+
+    fn push(&mut self, val: T) -> Option<T> {
+        modify!(self.push(val))
+    }
+
+    fn dequeue(&mut self) -> Option<T> {
+        modify!(self.dequeue())
+    }
+
+    fn remove_first_matching<F>(&mut self, f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        modify!(self.remove_first_matching(f))
+    }
+
+    fn empty(&mut self) {
+        modify!(self.empty())
+    }
+
+    fn retain<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        modify!(self.retain(f))
+    }
+
+    fn retain_mut<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        modify!(self.retain_mut(f))
+    }
+
+    fn position<F>(&self, f: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        inspect!(self.position(f))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate alloc;
+
+    use super::super::queue::Queue;
+    use super::super::ring_buffer::RingBuffer;
+    use super::*;
+
+    // A queue that claims to be full one element too early.
+    struct FullTooEarly<'a>(RingBuffer<'a, usize>);
+
+    impl Queue<usize> for FullTooEarly<'_> {
+        fn has_elements(&self) -> bool {
+            self.0.has_elements()
+        }
+
+        fn is_full(&self) -> bool {
+            self.0.available_len() <= 1
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.capacity()
+        }
+
+        fn enqueue(&mut self, val: usize) -> bool {
+            self.0.enqueue(val)
+        }
+
+        fn push(&mut self, val: usize) -> Option<usize> {
+            self.0.push(val)
+        }
+
+        fn dequeue(&mut self) -> Option<usize> {
+            self.0.dequeue()
+        }
+
+        fn remove_first_matching<F>(&mut self, f: F) -> Option<usize>
+        where
+            F: FnMut(&usize) -> bool,
+        {
+            self.0.remove_first_matching(f)
+        }
+
+        fn empty(&mut self) {
+            self.0.empty()
+        }
+
+        fn retain<F>(&mut self, f: F) -> usize
+        where
+            F: FnMut(&usize) -> bool,
+        {
+            self.0.retain(f)
+        }
+
+        fn retain_mut<F>(&mut self, f: F) -> usize
+        where
+            F: FnMut(&mut usize) -> bool,
+        {
+            self.0.retain_mut(f)
+        }
+
+        fn position<F>(&self, f: F) -> Option<usize>
+        where
+            F: FnMut(&usize) -> bool,
+        {
+            self.0.position(f)
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut ring = [0; 4];
+        let mut buf = CheckedQueue::new(RingBuffer::new(&mut ring));
+
+        for i in 0..TRACE_LEN {
+            buf.push(i);
+        }
+        assert_eq!(buf.dequeue(), Some(TRACE_LEN - 3));
+        assert!(buf.has_elements());
+
+        let mut calls = buf.trace().iter().skip(TRACE_LEN - 3);
+        assert_eq!(calls.next(), Some(&Call { op: "push", len: 3 }));
+        assert_eq!(calls.next(), Some(&Call { op: "push", len: 3 }));
+        assert_eq!(calls.next(), Some(&Call { op: "dequeue", len: 2 }));
+        assert_eq!(calls.next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "enqueue broke the queue: is_full is true with available_len 1")]
+    fn test_report() {
+        let mut ring = [0; 4];
+        let mut buf = CheckedQueue::new(FullTooEarly(RingBuffer::new(&mut ring)));
+
+        assert!(buf.enqueue(1));
+        assert!(buf.enqueue(2));
+    }
+
+    crate::queue_conformance_tests!(conformance, || {
+        CheckedQueue::new(RingBuffer::new(alloc::vec![0; 10].leak()))
+    });
+}
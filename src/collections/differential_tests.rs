@@ -0,0 +1,146 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Differential property tests of `RingBuffer` against `VecDeque`.
+//!
+//! Random sequences of operations are run on a `RingBuffer` of random capacity,
+//! whose head starts at a random offset in the ring, and on a `VecDeque` that
+//! is limited to the same capacity. They must agree on every return value and
+//! on the contents after every step. When they don't, proptest shrinks the
+//! sequence to a minimal failing one.
+
+extern crate alloc;
+
+use super::queue::Queue;
+use super::ring_buffer::RingBuffer;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use proptest::collection::vec as vec_of;
+use proptest::prelude::*;
+
+// Elements are drawn from a small range, so that predicates match some of them.
+const MAX_VALUE: i32 = 8;
+const MAX_CAPACITY: usize = 16;
+const MAX_OPS: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Enqueue(i32),
+    Push(i32),
+    Dequeue,
+    // Removes the first element equal to the value.
+    RemoveFirstMatching(i32),
+    // Keeps the elements that are not multiples of the value.
+    Retain(i32),
+    Empty,
+    AsSlices,
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (0..MAX_VALUE).prop_map(Op::Enqueue),
+        (0..MAX_VALUE).prop_map(Op::Push),
+        Just(Op::Dequeue),
+        (0..MAX_VALUE).prop_map(Op::RemoveFirstMatching),
+        (2..MAX_VALUE).prop_map(Op::Retain),
+        Just(Op::Empty),
+        Just(Op::AsSlices),
+    ]
+}
+
+// A capacity, and the offset of the head in a ring of `capacity + 1` slots.
+fn capacity_and_offset() -> impl Strategy<Value = (usize, usize)> {
+    (1..=MAX_CAPACITY).prop_flat_map(|capacity| (Just(capacity), 0..=capacity))
+}
+
+// The result of an operation, to compare those of both queues.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Bool(bool),
+    Element(Option<i32>),
+    Count(usize),
+    Contents(Vec<i32>),
+    None,
+}
+
+fn apply_ring_buffer(buf: &mut RingBuffer<i32>, op: Op) -> Outcome {
+    match op {
+        Op::Enqueue(val) => Outcome::Bool(buf.enqueue(val)),
+        Op::Push(val) => Outcome::Element(buf.push(val)),
+        Op::Dequeue => Outcome::Element(buf.dequeue()),
+        Op::RemoveFirstMatching(val) => Outcome::Element(buf.remove_first_matching(|&x| x == val)),
+        Op::Retain(div) => Outcome::Count(buf.retain(|&x| x % div != 0)),
+        Op::Empty => {
+            buf.empty();
+            Outcome::None
+        }
+        Op::AsSlices => Outcome::Contents(contents(buf)),
+    }
+}
+
+fn apply_vec_deque(deque: &mut VecDeque<i32>, capacity: usize, op: Op) -> Outcome {
+    match op {
+        Op::Enqueue(val) => Outcome::Bool(if deque.len() < capacity {
+            deque.push_back(val);
+            true
+        } else {
+            false
+        }),
+        Op::Push(val) => {
+            let front = if deque.len() == capacity {
+                deque.pop_front()
+            } else {
+                None
+            };
+            deque.push_back(val);
+            Outcome::Element(front)
+        }
+        Op::Dequeue => Outcome::Element(deque.pop_front()),
+        Op::RemoveFirstMatching(val) => {
+            Outcome::Element(deque.iter().position(|&x| x == val).and_then(|i| deque.remove(i)))
+        }
+        Op::Retain(div) => {
+            let len = deque.len();
+            deque.retain(|&x| x % div != 0);
+            Outcome::Count(len - deque.len())
+        }
+        Op::Empty => {
+            deque.clear();
+            Outcome::None
+        }
+        Op::AsSlices => Outcome::Contents(deque.iter().copied().collect()),
+    }
+}
+
+// The contents of `buf`, front first, checking the shape of `as_slices`.
+fn contents(buf: &RingBuffer<i32>) -> Vec<i32> {
+    let (front, back) = buf.as_slices();
+    assert_eq!(front.is_some(), buf.has_elements());
+    assert!(back.is_none() || back.is_some_and(|back| !back.is_empty()));
+    let mut contents = Vec::new();
+    contents.extend_from_slice(front.unwrap_or(&[]));
+    contents.extend_from_slice(back.unwrap_or(&[]));
+    contents
+}
+
+proptest! {
+    #[test]
+    fn matches_vec_deque((capacity, offset) in capacity_and_offset(), ops in vec_of(op(), 0..MAX_OPS)) {
+        let mut ring = vec![0; capacity + 1];
+        let mut buf = RingBuffer::try_from_parts(&mut ring, offset, offset).unwrap();
+        let mut deque = VecDeque::with_capacity(capacity);
+
+        for (step, &op) in ops.iter().enumerate() {
+            let outcome = apply_ring_buffer(&mut buf, op);
+            let expected = apply_vec_deque(&mut deque, capacity, op);
+            prop_assert_eq!(outcome, expected, "step {}: {:?}", step, op);
+
+            prop_assert_eq!(buf.len(), deque.len(), "step {}: {:?}", step, op);
+            prop_assert_eq!(buf.has_elements(), !deque.is_empty(), "step {}: {:?}", step, op);
+            prop_assert_eq!(buf.is_full(), deque.len() == capacity, "step {}: {:?}", step, op);
+            prop_assert_eq!(buf.available_len(), capacity - deque.len(), "step {}: {:?}", step, op);
+            prop_assert_eq!(contents(&buf), deque.iter().copied().collect::<Vec<_>>(), "step {}: {:?}", step, op);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Kani proofs for the `queue::Queue` operations of `RingBuffer`, as well as
+//! `as_slices`.
+//!
+//! Each proof starts from an arbitrary valid ring buffer of at most `MAX_LEN`
+//! slots, with arbitrary contents, and shows that the operation:
+//! - doesn't panic;
+//! - preserves the invariants (`RingBuffer::validate`);
+//! - behaves like the `model::Seq` model, through `model::Shadowed`.
+//!
+//! Under Kani, `assert_invariant!` checks every fact before it is assumed, so
+//! with the `assume-*` features the proofs also show that each
+//! `assert_unchecked` holds where it is used:
+//!
+//! ```sh
+//! cargo kani --features assume-invariants
+//! ```
+
+use super::model::Shadowed;
+use super::queue::Queue;
+use super::ring_buffer::RingBuffer;
+
+const MAX_LEN: usize = 4;
+
+// Runs `op` on an arbitrary valid ring buffer shadowed by its model, then
+// checks that the invariants still hold.
+fn check(op: impl FnOnce(&mut Shadowed<'_, '_, i32>)) {
+    let mut ring: [i32; MAX_LEN] = kani::any();
+    let mut model = [0; MAX_LEN - 1];
+    let mut verdicts = [false; MAX_LEN - 1];
+    let len: usize = kani::any();
+    let head: usize = kani::any();
+    let tail: usize = kani::any();
+    kani::assume(1 < len && len <= MAX_LEN);
+    kani::assume(head < len);
+    kani::assume(tail < len);
+
+    let buf = unsafe { RingBuffer::from_parts(&mut ring[..len], head, tail) };
+    let mut buf = Shadowed::new(buf, &mut model[..len - 1], &mut verdicts[..len - 1]);
+    op(&mut buf);
+    assert!(buf.buf().validate().is_ok());
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn has_elements() {
+    check(|buf| {
+        buf.has_elements();
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn is_full() {
+    check(|buf| {
+        buf.is_full();
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn len() {
+    check(|buf| {
+        buf.len();
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn enqueue() {
+    check(|buf| {
+        buf.enqueue(kani::any());
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn push() {
+    check(|buf| {
+        buf.push(kani::any());
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn dequeue() {
+    check(|buf| {
+        buf.dequeue();
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn remove_first_matching() {
+    let target: i32 = kani::any();
+    check(|buf| {
+        buf.remove_first_matching(|&x| x == target);
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn empty() {
+    check(|buf| buf.empty());
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn retain() {
+    let target: i32 = kani::any();
+    check(|buf| {
+        buf.retain(|&x| x < target);
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn retain_mut() {
+    let target: i32 = kani::any();
+    check(|buf| {
+        buf.retain_mut(|x| {
+            *x = x.wrapping_add(1);
+            *x < target
+        });
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn position() {
+    let target: i32 = kani::any();
+    check(|buf| {
+        buf.position(|&x| x == target);
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn as_slices() {
+    check(|buf| {
+        let expected = buf.model().as_slice();
+        let (front, back) = buf.buf().as_slices();
+        assert_eq!(front.is_some(), !expected.is_empty());
+        let front = front.unwrap_or(&[]);
+        let back = back.unwrap_or(&[]);
+        assert_eq!(front.len() + back.len(), expected.len());
+        assert!(front == &expected[..front.len()] && back == &expected[front.len()..]);
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn available_len() {
+    check(|buf| {
+        let expected = buf.model().capacity() - buf.model().as_slice().len();
+        assert_eq!(buf.buf().available_len(), expected);
+    });
+}
+
+#[kani::proof]
+#[kani::unwind(6)]
+fn capacity() {
+    check(|buf| {
+        let expected = buf.model().capacity();
+        assert_eq!(buf.buf().capacity(), expected);
+    });
+}
@@ -0,0 +1,58 @@
+// The struct invariants (which flux proves) are handled in one of three ways:
+// - with an `assume-*` feature, the matching fact is passed to the optimizer
+//   with `assert_unchecked` (`assume-invariants` enables all of them);
+// - with the `check-invariants` feature, they are all checked at runtime and a
+//   violation panics. This takes precedence: along with an `assume-*` feature,
+//   the fact is checked before it is assumed, so it is never assumed wrongly;
+// - otherwise, the compiler is told nothing.
+//
+// The `assert_invariants` method that applies this to every invariant of a
+// struct is generated by `#[derive_assumptions]` from its flux annotations.
+
+// Handles a single fact, which is assumed if `$feature` is enabled.
+//
+// Kani always checks the fact, before it is assumed, so that the proofs in
+// `kani_proofs.rs` show that every assumption holds where it is made.
+macro_rules! assert_invariant {
+    ($feature:literal, $cond:expr, $($msg:tt)+) => {
+        #[cfg(any(feature = "check-invariants", kani))]
+        assert!($cond, $($msg)+);
+        #[cfg(feature = $feature)]
+        unsafe {
+            core::hint::assert_unchecked($cond);
+        }
+    };
+}
+
+macro_rules! assert_invariants {
+    ($self:ident) => {
+        $self.assert_invariants();
+    };
+}
+
+// Same as `assert_invariants!`, for the `dst < len` fact in `retain_mut`,
+// which is not a struct invariant.
+macro_rules! assert_dst_in_bounds {
+    ($dst:ident, $len:ident) => {
+        assert_invariant!(
+            "assume-dst-in-bounds",
+            $dst < $len,
+            "retain invariant violated: dst ({}) < ring_len ({})",
+            $dst,
+            $len
+        );
+    };
+}
+
+pub mod array_ring_buffer;
+pub mod checked;
+#[cfg(test)]
+mod differential_tests;
+#[cfg(kani)]
+mod kani_proofs;
+#[cfg(any(test, kani, feature = "runtime-model"))]
+pub mod model;
+pub mod queue;
+pub mod ring_buffer;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
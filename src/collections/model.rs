@@ -0,0 +1,430 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A reference model of the logical contents of a queue, to check a
+//! `RingBuffer` against at runtime.
+//!
+//! The flux refinements of `RingBuffer` only track `(ring_len, hd, tl)`, which
+//! shows that its index arithmetic is safe but not that it is a FIFO queue,
+//! and this module doesn't change that: flux doesn't see the contents.
+//! Instead, [`Seq`] states the effect of every `queue::Queue` operation on the
+//! sequence of elements in the queue, as naively as possible: the front of the
+//! queue is always at index 0 and removing an element shifts all the following
+//! ones. [`Shadowed`] runs a `RingBuffer` and a `Seq` side by side, checking
+//! after every operation that they returned the same values and hold the same
+//! sequence. The unit tests and the Kani proofs (for small rings) use it.
+//!
+//! This is only built for tests and Kani proofs, or with the `runtime-model`
+//! feature. It is not a ghost model: nothing here is checked statically.
+
+use crate::collections::queue::{self, Queue};
+use crate::collections::ring_buffer::RingBuffer;
+use core::cell::Cell;
+use core::cmp::PartialEq;
+use core::fmt::Debug;
+use core::iter::Iterator;
+use core::marker::Copy;
+use core::ops::FnMut;
+use core::option::{Option, Option::{None, Some}};
+
+/// The sequence of elements in a queue, front first, with room for at most
+/// `cap` of them.
+#[flux_rs::refined_by(cap: int, len: int)]
+#[flux_rs::invariant(cap > 0)]
+#[flux_rs::invariant(len <= cap)]
+pub struct Seq<'a, T> {
+    #[field({&mut [T][cap] | cap > 0})]
+    elems: &'a mut [T],
+    #[field({usize[len] | len <= cap})]
+    len: usize,
+}
+
+impl<'a, T: Copy> Seq<'a, T> {
+    /// Creates an empty sequence holding at most `elems.len()` elements, the
+    /// capacity of a `RingBuffer` over `elems.len() + 1` slots.
+    #[flux_rs::sig(fn({&mut [T][@cap] | cap > 0}) -> Seq<T>[cap, 0])]
+    pub fn new(elems: &'a mut [T]) -> Self {
+        Seq { elems, len: 0 }
+    }
+
+    /// The elements of the sequence, front first.
+    #[flux_rs::sig(fn(&Seq<T>[@s]) -> &[T][s.len])]
+    pub fn as_slice(&self) -> &[T] {
+        &self.elems[..self.len]
+    }
+
+    // Removes the element at `index`, shifting the following ones forward.
+    #[flux_rs::sig(
+        fn(self: &strg Seq<T>[@old], usize{i: i < old.len}) -> T
+            ensures self: Seq<T>[old.cap, old.len - 1]
+    )]
+    fn remove_at(&mut self, index: usize) -> T {
+        let val = self.elems[index];
+        self.elems.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        val
+    }
+}
+
+#[flux_rs::assoc(fn length(s: Seq) -> int { s.len })]
+#[flux_rs::assoc(fn capacity(s: Seq) -> int { s.cap })]
+impl<T: Copy> queue::Queue<T> for Seq<'_, T> {
+    #[flux_rs::sig(fn(&Seq<T>[@s]) -> bool[s.len > 0])]
+    fn has_elements(&self) -> bool {
+        self.len > 0
+    }
+
+    #[flux_rs::sig(fn(&Seq<T>[@s]) -> bool[s.len == s.cap])]
+    fn is_full(&self) -> bool {
+        self.len == self.elems.len()
+    }
+
+    #[flux_rs::sig(fn(&Seq<T>[@s]) -> usize[s.len])]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[flux_rs::sig(fn(&Seq<T>[@s]) -> usize[s.cap - s.len])]
+    fn available_len(&self) -> usize {
+        self.elems.len() - self.len
+    }
+
+    #[flux_rs::sig(fn(&Seq<T>[@s]) -> usize[s.cap])]
+    fn capacity(&self) -> usize {
+        self.elems.len()
+    }
+
+    /// `seq ++ [val]`, unless the sequence is full.
+    #[flux_rs::sig(
+        fn(self: &strg Seq<T>[@old], _) -> bool[old.len < old.cap]
+            ensures self: Seq<T>[old.cap, if old.len < old.cap { old.len + 1 } else { old.len }]
+    )]
+    fn enqueue(&mut self, val: T) -> bool {
+        if self.len == self.elems.len() {
+            false
+        } else {
+            self.elems[self.len] = val;
+            self.len += 1;
+            true
+        }
+    }
+
+    /// `seq[1..] ++ [val]` returning `seq[0]` if the sequence is full,
+    /// otherwise `seq ++ [val]`.
+    #[flux_rs::sig(
+        fn(self: &strg Seq<T>[@old], _) -> Option<T>[old.len == old.cap]
+            ensures self: Seq<T>[old.cap, if old.len < old.cap { old.len + 1 } else { old.len }]
+    )]
+    fn push(&mut self, val: T) -> Option<T> {
+        let result = if self.len == self.elems.len() {
+            Some(self.remove_at(0))
+        } else {
+            None
+        };
+
+        self.elems[self.len] = val;
+        self.len += 1;
+        result
+    }
+
+    /// `seq[1..]` returning `seq[0]`, unless the sequence is empty.
+    #[flux_rs::sig(
+        fn(self: &strg Seq<T>[@old]) -> Option<T>[old.len > 0]
+            ensures self: Seq<T>[old.cap, if old.len > 0 { old.len - 1 } else { 0 }]
+    )]
+    fn dequeue(&mut self) -> Option<T> {
+        if self.len > 0 {
+            Some(self.remove_at(0))
+        } else {
+            None
+        }
+    }
+
+    /// `seq[..i] ++ seq[i + 1..]` returning `seq[i]`, where `i` is the first
+    /// index whose element matches.
+    #[flux_rs::sig(
+        fn(self: &strg Seq<T>[@old], _) -> Option<T>[@removed]
+            ensures self: Seq<T>[old.cap, if removed { old.len - 1 } else { old.len }]
+    )]
+    fn remove_first_matching<F>(&mut self, f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.position(f).map(|index| self.remove_at(index))
+    }
+
+    /// `[]`.
+    #[flux_rs::sig(fn(self: &strg Seq<T>[@old]) ensures self: Seq<T>[old.cap, 0])]
+    fn empty(&mut self) {
+        self.len = 0;
+    }
+
+    /// The elements that match, in the same order.
+    #[flux_rs::sig(
+        fn(self: &strg Seq<T>[@old], _) -> usize[@removed]
+            ensures self: Seq<T>[old.cap, old.len - removed]
+    )]
+    fn retain<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_mut(|elem| f(elem))
+    }
+
+    /// The elements that match after being modified, in the same order.
+    #[flux_rs::sig(
+        fn(self: &strg Seq<T>[@old], _) -> usize[@removed]
+            ensures self: Seq<T>[old.cap, old.len - removed]
+    )]
+    fn retain_mut<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(&mut self.elems[i]) {
+                self.elems[kept] = self.elems[i];
+                kept += 1;
+            }
+        }
+        let removed = self.len - kept;
+        self.len = kept;
+        removed
+    }
+
+    /// The first index whose element matches.
+    #[flux_rs::sig(fn(&Seq<T>[@s], _) -> Option<usize{i: i < s.len}>)]
+    fn position<F>(&self, f: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.as_slice().iter().position(f)
+    }
+}
+
+/// A `RingBuffer` shadowed by its [`Seq`] model.
+///
+/// Every `queue::Queue` operation is applied to both, and panics if they
+/// return different values or if the ring buffer no longer holds exactly the
+/// sequence of the model.
+///
+/// Predicates are only called on the elements of the model, and their verdicts
+/// are then replayed on the ring buffer, so stateful predicates see each
+/// element once. This panics if the ring buffer asks for a different number of
+/// verdicts than the model did.
+///
+/// Flux ignores it: it only forwards to a `RingBuffer` and a `Seq`, which flux
+/// checks on their own, and asserts facts about their contents, which flux
+/// doesn't track.
+#[flux_rs::ignore]
+pub struct Shadowed<'a, 'm, T: Copy> {
+    buf: RingBuffer<'a, T>,
+    model: Seq<'m, T>,
+    // The verdicts of the last predicate on the elements of the model, in
+    // order.
+    verdicts: &'m [Cell<bool>],
+}
+
+#[flux_rs::ignore]
+impl<'a, 'm, T: Copy + PartialEq + Debug> Shadowed<'a, 'm, T> {
+    /// Shadows `buf` with a model stored in `model`, which must have room for
+    /// exactly as many elements as `buf` (`ring.len() - 1`). `verdicts` holds
+    /// the verdicts of predicates, one per element of the model, so it must be
+    /// as long as `model`.
+    pub fn new(buf: RingBuffer<'a, T>, model: &'m mut [T], verdicts: &'m mut [bool]) -> Self {
+        assert_eq!(
+            model.len(),
+            buf.len() + buf.available_len(),
+            "the model must have the same capacity as the ring buffer"
+        );
+        assert_eq!(
+            verdicts.len(),
+            model.len(),
+            "there must be room for a verdict per element of the model"
+        );
+        let mut model = Seq::new(model);
+        let (front, back) = buf.as_slices();
+        for &val in front.into_iter().chain(back).flatten() {
+            model.enqueue(val);
+        }
+        Shadowed {
+            buf,
+            model,
+            verdicts: Cell::from_mut(verdicts).as_slice_of_cells(),
+        }
+    }
+
+    pub fn buf(&self) -> &RingBuffer<'a, T> {
+        &self.buf
+    }
+
+    pub fn model(&self) -> &Seq<'m, T> {
+        &self.model
+    }
+
+    pub fn into_inner(self) -> RingBuffer<'a, T> {
+        self.buf
+    }
+
+    // Checks that the ring buffer holds the sequence of the model.
+    fn check(&self, op: &str) {
+        let expected = self.model.as_slice();
+        let (front, back) = self.buf.as_slices();
+        let front = front.unwrap_or(&[]);
+        let back = back.unwrap_or(&[]);
+        assert!(
+            front.len() + back.len() == expected.len()
+                && front == &expected[..front.len()]
+                && back == &expected[front.len()..],
+            "{} diverged from the model: {:?} {:?} != {:?}",
+            op,
+            front,
+            back,
+            expected
+        );
+        assert_eq!(
+            self.buf.available_len(),
+            self.model.available_len(),
+            "{} diverged from the model",
+            op
+        );
+    }
+}
+
+// Calls `$method` on both the ring buffer and the model, and checks that they
+// agree on the result and on the new contents.
+macro_rules! shadow {
+    ($self:ident . $method:ident($($arg:expr),*)) => {{
+        let result = $self.buf.$method($($arg),*);
+        let expected = $self.model.$method($($arg),*);
+        assert_eq!(
+            result,
+            expected,
+            concat!(stringify!($method), " returned a different value than the model")
+        );
+        $self.check(stringify!($method));
+        result
+    }};
+}
+
+// Calls `$method` with the predicate `$f` on the model, then replays its
+// verdicts on the ring buffer, and checks that they agree on the result and on
+// the new contents. `$on_kept` is run on the elements of the ring buffer that
+// are kept.
+macro_rules! shadow_predicate {
+    ($self:ident . $method:ident($f:ident) $(, kept: |$elem:ident| $on_kept:expr)?) => {{
+        let op = stringify!($method);
+        let verdicts = $self.verdicts;
+        let mut f = $f;
+        let mut recorded = 0;
+        let expected = $self.model.$method(|elem| {
+            let verdict = f(elem);
+            verdicts[recorded].set(verdict);
+            recorded += 1;
+            verdict
+        });
+        let mut replayed = 0;
+        #[allow(unused_variables)]
+        let result = $self.buf.$method(|elem| {
+            assert!(replayed < recorded, "{} called the predicate more often than the model", op);
+            let verdict = verdicts[replayed].get();
+            replayed += 1;
+            $(if verdict {
+                let $elem = elem;
+                $on_kept;
+            })?
+            verdict
+        });
+        assert_eq!(
+            replayed, recorded,
+            "{} called the predicate a different number of times than the model",
+            op
+        );
+        assert_eq!(result, expected, "{} returned a different value than the model", op);
+        $self.check(op);
+        result
+    }};
+}
+
+#[flux_rs::ignore]
+impl<T: Copy + PartialEq + Debug> queue::Queue<T> for Shadowed<'_, '_, T> {
+    fn has_elements(&self) -> bool {
+        let result = self.buf.has_elements();
+        assert_eq!(result, self.model.has_elements(), "has_elements differs from the model");
+        result
+    }
+
+    fn is_full(&self) -> bool {
+        let result = self.buf.is_full();
+        assert_eq!(result, self.model.is_full(), "is_full differs from the model");
+        result
+    }
+
+    fn len(&self) -> usize {
+        let result = self.buf.len();
+        assert_eq!(result, self.model.len(), "len differs from the model");
+        result
+    }
+
+    fn available_len(&self) -> usize {
+        let result = self.buf.available_len();
+        assert_eq!(result, self.model.available_len(), "available_len differs from the model");
+        result
+    }
+
+    fn capacity(&self) -> usize {
+        let result = self.buf.capacity();
+        assert_eq!(result, self.model.capacity(), "capacity differs from the model");
+        result
+    }
+
+    fn enqueue(&mut self, val: T) -> bool {
+        shadow!(self.enqueue(val))
+    }
+
+    fn push(&mut self, val: T) -> Option<T> {
+        shadow!(self.push(val))
+    }
+
+    fn dequeue(&mut self) -> Option<T> {
+        shadow!(self.dequeue())
+    }
+
+    fn remove_first_matching<F>(&mut self, f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        shadow_predicate!(self.remove_first_matching(f))
+    }
+
+    fn empty(&mut self) {
+        shadow!(self.empty())
+    }
+
+    fn retain<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        shadow_predicate!(self.retain(f))
+    }
+
+    fn retain_mut<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        // The kept elements of the ring buffer are given the values that `f`
+        // left in the model, in order.
+        let mut kept = 0;
+        shadow_predicate!(self.retain_mut(f), kept: |elem| {
+            *elem = self.model.as_slice()[kept];
+            kept += 1;
+        })
+    }
+
+    fn position<F>(&self, f: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        shadow_predicate!(self.position(f))
+    }
+}
@@ -0,0 +1,127 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2022.
+
+//! Interface for queue structure.
+use core::option::{Option, Option::{None, Some}};
+use core::ops::FnMut;
+
+/// The contracts of the methods are stated in terms of two abstract
+/// refinements, so that code generic over `Q: Queue<T>` can be verified:
+/// `length(q)` is the number of elements in the queue, and `capacity(q)` how
+/// many it can hold.
+#[flux_rs::assoc(fn length(self: Self) -> int)]
+#[flux_rs::assoc(fn capacity(self: Self) -> int)]
+pub trait Queue<T> {
+    /// Returns true if there are any items in the queue, false otherwise.
+    #[flux_rs::sig(fn(&Self[@q]) -> bool[<Self as Queue<T>>::length(q) > 0])]
+    fn has_elements(&self) -> bool;
+
+    /// Returns true if the queue is full, false otherwise.
+    #[flux_rs::sig(fn(&Self[@q]) -> bool[<Self as Queue<T>>::length(q) == <Self as Queue<T>>::capacity(q)])]
+    fn is_full(&self) -> bool;
+
+    /// Returns how many elements are in the queue.
+    #[flux_rs::sig(fn(&Self[@q]) -> usize[<Self as Queue<T>>::length(q)])]
+    fn len(&self) -> usize;
+
+    /// Returns how many more elements can be added before the queue is full.
+    #[flux_rs::sig(fn(&Self[@q]) -> usize[<Self as Queue<T>>::capacity(q) - <Self as Queue<T>>::length(q)])]
+    fn available_len(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    /// Returns how many elements the queue can hold.
+    #[flux_rs::sig(fn(&Self[@q]) -> usize[<Self as Queue<T>>::capacity(q)])]
+    fn capacity(&self) -> usize;
+
+    /// If the queue isn't full, add a new element to the back of the queue.
+    /// Returns whether the element was added.
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old], _) -> bool[<Self as Queue<T>>::length(old) < <Self as Queue<T>>::capacity(old)]
+            ensures self: Self{ new:
+                <Self as Queue<T>>::capacity(new) == <Self as Queue<T>>::capacity(old)
+                &&
+                (<Self as Queue<T>>::length(old) < <Self as Queue<T>>::capacity(old) => <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old) + 1)
+                &&
+                (<Self as Queue<T>>::length(old) == <Self as Queue<T>>::capacity(old) => <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old))
+            }
+    )]
+    fn enqueue(&mut self, val: T) -> bool;
+
+    /// Add a new element to the back of the queue, poping one from the front if necessary.
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old], _) -> Option<T>[<Self as Queue<T>>::length(old) == <Self as Queue<T>>::capacity(old)]
+            ensures self: Self{ new:
+                <Self as Queue<T>>::capacity(new) == <Self as Queue<T>>::capacity(old)
+                &&
+                (<Self as Queue<T>>::length(old) < <Self as Queue<T>>::capacity(old) => <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old) + 1)
+                &&
+                (<Self as Queue<T>>::length(old) == <Self as Queue<T>>::capacity(old) => <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old))
+            }
+    )]
+    fn push(&mut self, val: T) -> Option<T>;
+
+    /// Remove the element from the front of the queue.
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old]) -> Option<T>[<Self as Queue<T>>::length(old) > 0]
+            ensures self: Self{ new:
+                <Self as Queue<T>>::capacity(new) == <Self as Queue<T>>::capacity(old)
+                &&
+                (<Self as Queue<T>>::length(old) > 0 => <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old) - 1)
+                &&
+                (<Self as Queue<T>>::length(old) == 0 => <Self as Queue<T>>::length(new) == 0)
+            }
+    )]
+    fn dequeue(&mut self) -> Option<T>;
+
+    /// Remove and return one (the first) element that matches the predicate.
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old], _) -> Option<T>[@removed]
+            ensures self: Self{ new:
+                <Self as Queue<T>>::capacity(new) == <Self as Queue<T>>::capacity(old)
+                &&
+                (removed => <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old) - 1)
+                &&
+                (!removed => <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old))
+            }
+    )]
+    fn remove_first_matching<F>(&mut self, f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool;
+
+    /// Remove all elements from the ring buffer.
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old])
+            ensures self: Self{ new: <Self as Queue<T>>::capacity(new) == <Self as Queue<T>>::capacity(old) && <Self as Queue<T>>::length(new) == 0 }
+    )]
+    fn empty(&mut self);
+
+    /// Retains only the elements that satisfy the predicate.
+    /// Returns how many elements were removed.
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old], _) -> usize[@removed]
+            ensures self: Self{ new: <Self as Queue<T>>::capacity(new) == <Self as Queue<T>>::capacity(old) && <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old) - removed }
+    )]
+    fn retain<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&T) -> bool;
+
+    /// Retains only the elements that satisfy the predicate, which may also
+    /// modify them in place.
+    /// Returns how many elements were removed.
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old], _) -> usize[@removed]
+            ensures self: Self{ new: <Self as Queue<T>>::capacity(new) == <Self as Queue<T>>::capacity(old) && <Self as Queue<T>>::length(new) == <Self as Queue<T>>::length(old) - removed }
+    )]
+    fn retain_mut<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&mut T) -> bool;
+
+    /// Returns the logical index (counted from the front of the queue) of the
+    /// first element that matches the predicate.
+    #[flux_rs::sig(fn(&Self[@q], _) -> Option<usize{i: i < <Self as Queue<T>>::length(q)}>)]
+    fn position<F>(&self, f: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool;
+}
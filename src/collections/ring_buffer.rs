@@ -0,0 +1,1648 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2022.
+
+//! Implementation of a ring buffer.
+
+
+use crate::collections::queue;
+use derive_assumptions::derive_assumptions;
+use core::option::{Option, Option::{None, Some}};
+use core::result::{Result, Result::{Err, Ok}};
+use core::ops::{Drop, FnMut};
+use core::iter::Iterator;
+use core::marker::Copy;
+
+#[derive_assumptions(features = [
+    "assume-ring-len",
+    "assume-head-in-bounds",
+    "assume-tail-in-bounds",
+])]
+#[flux_rs::refined_by(ring_len: int, hd: int, tl: int)]
+#[flux_rs::invariant(ring_len > 1)]
+#[flux_rs::invariant(hd < ring_len)]
+#[flux_rs::invariant(tl < ring_len)]
+pub struct RingBuffer<'a, T: 'a> {
+    #[field({&mut [T][ring_len] | ring_len > 1})]
+    ring: &'a mut [T],
+    #[field({usize[hd] | hd < ring_len})]
+    head: usize,
+    #[field({usize[tl] | tl < ring_len})]
+    tail: usize,
+}
+
+flux_rs::defs! {
+    fn next_index(x:int, ring_len: int) -> int { (x + 1) % ring_len }
+    fn empty(rb: RingBuffer) -> bool { rb.hd == rb.tl }
+    fn full(rb: RingBuffer) -> bool { rb.hd == next_index(rb.tl, rb.ring_len) }
+    fn next_hd(rb: RingBuffer) -> int { next_index(rb.hd, rb.ring_len) }
+    fn next_tl(rb: RingBuffer) -> int { next_index(rb.tl, rb.ring_len) }
+    fn prev_index(x:int, ring_len: int) -> int { (x + ring_len - 1) % ring_len }
+    fn prev_tl(rb: RingBuffer) -> int { prev_index(rb.tl, rb.ring_len) }
+    fn dist(from: int, to: int, ring_len: int) -> int { if from <= to { to - from } else { ring_len - from + to } }
+    fn len(rb: RingBuffer) -> int { dist(rb.hd, rb.tl, rb.ring_len) }
+    fn available_len(rb: RingBuffer) -> int { rb.ring_len - 1 - len(rb) }
+    // Lengths of the slices returned by `as_slices`, which add up to `len(rb)`.
+    fn front_len(rb: RingBuffer) -> int { if rb.hd <= rb.tl { rb.tl - rb.hd } else { rb.ring_len - rb.hd } }
+    fn back_len(rb: RingBuffer) -> int { if rb.hd <= rb.tl { 0 } else { rb.tl } }
+    fn can_split(rb: RingBuffer, at: int, other: RingBuffer) -> bool {
+        at <= len(rb) && len(rb) - at <= available_len(other)
+    }
+    fn same_head(old: RingBuffer, new: RingBuffer) -> bool {
+        new.ring_len == old.ring_len && new.hd == old.hd
+    }
+    // `new` is `old` with one element removed, and the same head.
+    fn removed_one(old: RingBuffer, new: RingBuffer) -> bool {
+        same_head(old, new) && len(new) == len(old) - 1
+    }
+
+    // Loop invariants of `retain_mut`: `src` walks from the head to the tail,
+    // and `dst` trails it, so both stay within the elements of the buffer.
+    qualifier SrcInRange(src: int, hd: int, tl: int, ring_len: int) {
+        src < ring_len && dist(hd, src, ring_len) <= dist(hd, tl, ring_len)
+    }
+    qualifier DstTrailsSrc(dst: int, src: int, hd: int, ring_len: int) {
+        dst < ring_len && dist(hd, dst, ring_len) <= dist(hd, src, ring_len)
+    }
+    qualifier RemovedCount(removed: int, src: int, dst: int, hd: int, ring_len: int) {
+        removed == dist(hd, src, ring_len) - dist(hd, dst, ring_len)
+    }
+
+    // Loop invariant of `remove_first_matching`, which walks `slot` and `next`
+    // towards the tail (both `SrcInRange`) to shift the elements after the
+    // removed one: `next` stays one slot ahead, so the tail ends up one slot
+    // before the old one.
+    qualifier NextSlot(next: int, slot: int, ring_len: int) {
+        next == next_index(slot, ring_len)
+    }
+
+    // Loop invariant of `position`: `index` is the logical index of `slot`.
+    qualifier IndexOfSlot(index: int, slot: int, hd: int, ring_len: int) {
+        index == dist(hd, slot, ring_len)
+    }
+}
+
+/// A [`RingBuffer`] invariant that does not hold, as reported by
+/// [`RingBuffer::validate`] and [`RingBuffer::try_from_parts`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// The ring has fewer than 2 slots (`ring_len > 1`).
+    RingTooShort,
+    /// The head is out of bounds (`head < ring_len`).
+    HeadOutOfBounds,
+    /// The tail is out of bounds (`tail < ring_len`).
+    TailOutOfBounds,
+}
+
+impl InvariantViolation {
+    // Returns the first invariant that the parts of a ring buffer break, if
+    // any.
+    #[flux_rs::sig(
+        fn(usize[@ring_len], usize[@hd], usize[@tl])
+            -> Option<InvariantViolation>[!(ring_len > 1 && hd < ring_len && tl < ring_len)]
+    )]
+    #[inline(always)]
+    fn find(ring_len: usize, head: usize, tail: usize) -> Option<InvariantViolation> {
+        if ring_len < 2 {
+            Some(InvariantViolation::RingTooShort)
+        } else if head >= ring_len {
+            Some(InvariantViolation::HeadOutOfBounds)
+        } else if tail >= ring_len {
+            Some(InvariantViolation::TailOutOfBounds)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T: Copy> RingBuffer<'a, T> {
+    #[flux_rs::sig(fn({&mut [T][@ring_len] | ring_len > 1}) -> RingBuffer<T>[ring_len, 0, 0])]
+    #[inline(never)]
+    pub fn new(ring: &'a mut [T]) -> RingBuffer<'a, T> {
+        RingBuffer {
+            head: 0,
+            tail: 0,
+            ring,
+        }
+    }
+
+    /// Creates a ring buffer from existing storage and indices, e.g. ones
+    /// obtained from [`RingBuffer::into_parts`] before a warm reset. Unlike
+    /// [`RingBuffer::new`], the contents of `ring` between `head` and `tail`
+    /// are kept.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ring.len() > 1`, `head < ring.len()` and
+    /// `tail < ring.len()`. The other methods assume these invariants without
+    /// checking them.
+    #[flux_rs::sig(
+        fn({&mut [T][@ring_len] | ring_len > 1}, usize{hd: hd < ring_len}, usize{tl: tl < ring_len})
+            -> RingBuffer<T>[ring_len, hd, tl]
+    )]
+    #[inline(never)]
+    pub unsafe fn from_parts(ring: &'a mut [T], head: usize, tail: usize) -> RingBuffer<'a, T> {
+        RingBuffer { ring, head, tail }
+    }
+
+    /// Creates a ring buffer from existing storage and indices, checking the
+    /// invariants at runtime.
+    ///
+    /// If an invariant does not hold, the violation and `ring` are handed
+    /// back in the `Err` variant, so that the caller can fall back to
+    /// [`RingBuffer::new`].
+    #[flux_rs::sig(
+        fn(&mut [T][@ring_len], usize[@hd], usize[@tl])
+            -> Result<RingBuffer<T>[ring_len, hd, tl], (InvariantViolation, &mut [T][ring_len])>
+    )]
+    #[inline(never)]
+    pub fn try_from_parts(
+        ring: &'a mut [T],
+        head: usize,
+        tail: usize,
+    ) -> Result<RingBuffer<'a, T>, (InvariantViolation, &'a mut [T])> {
+        match InvariantViolation::find(ring.len(), head, tail) {
+            Some(violation) => Err((violation, ring)),
+            None => Ok(RingBuffer { ring, head, tail }),
+        }
+    }
+
+    /// Consumes the ring buffer and returns its storage, head and tail, which
+    /// can be passed back to [`RingBuffer::from_parts`].
+    #[flux_rs::sig(
+        fn(RingBuffer<T>[@rb]) -> (&mut [T][rb.ring_len], usize[rb.hd], usize[rb.tl])
+    )]
+    #[inline(never)]
+    pub fn into_parts(self) -> (&'a mut [T], usize, usize) {
+        (self.ring, self.head, self.tail)
+    }
+
+    /// Checks at runtime the invariants that `flux` proves and that the other
+    /// methods rely on.
+    ///
+    /// This can only fail if the ring buffer was built from invalid parts with
+    /// [`RingBuffer::from_parts`].
+    #[inline(never)]
+    pub fn validate(&self) -> Result<(), InvariantViolation> {
+        match InvariantViolation::find(self.ring.len(), self.head, self.tail) {
+            Some(violation) => Err(violation),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves the contents of the ring buffer to `new_storage`, which can be
+    /// larger or smaller than the current storage.
+    ///
+    /// The elements are copied in logical order starting at index 0 of
+    /// `new_storage`. If they don't fit (`new_storage` must be strictly longer
+    /// than the current length, and at least 2 elements), the buffer and
+    /// `new_storage` are handed back unchanged in the `Err` variant.
+    #[flux_rs::sig(
+        fn(RingBuffer<T>[@old], &mut [T][@n])
+            -> Result<RingBuffer<T>[n, 0, len(old)], (RingBuffer<T>[old], &mut [T][n])>
+    )]
+    #[inline(never)]
+    pub fn migrate<'b>(
+        self,
+        new_storage: &'b mut [T],
+    ) -> Result<RingBuffer<'b, T>, (RingBuffer<'a, T>, &'b mut [T])> {
+        assert_invariants!(self);
+        let count = queue::Queue::len(&self);
+        if new_storage.len() <= count || new_storage.len() < 2 {
+            return Err((self, new_storage));
+        }
+
+        if self.head <= self.tail {
+            new_storage[..count].copy_from_slice(&self.ring[self.head..self.tail]);
+        } else {
+            let (left, right) = self.ring.split_at(self.head);
+            let (first, second) = new_storage[..count].split_at_mut(right.len());
+            first.copy_from_slice(right);
+            second.copy_from_slice(&left[..self.tail]);
+        }
+        Ok(RingBuffer {
+            head: 0,
+            tail: count,
+            ring: new_storage,
+        })
+    }
+
+    /// Consumes the ring buffer and returns its backing storage.
+    ///
+    /// The storage is returned as-is: it still holds the elements, laid out
+    /// from the head, and any stale values in the unused slots.
+    #[flux_rs::sig(fn(RingBuffer<T>[@rb]) -> &mut [T][rb.ring_len])]
+    #[inline(never)]
+    pub fn reclaim(self) -> &'a mut [T] {
+        self.ring
+    }
+
+    /// Returns the number of elements that can be enqueued until the ring buffer is full.
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> usize[available_len(rb)])]
+    #[inline(never)]
+    pub fn available_len(&self) -> usize {
+        // The maximum capacity of the queue is ring.len - 1, because head == tail for the empty
+        // queue. This can't underflow: flux proves that len < ring.len.
+        assert_invariants!(self);
+        self.ring.len() - 1 - queue::Queue::len(self)
+    }
+
+    /// Returns up to 2 slices that together form the contents of the ring buffer.
+    ///
+    /// Returns:
+    /// - `(None, None)` if the buffer is empty.
+    /// - `(Some(slice), None)` if the head is before the tail (therefore all the contents is
+    /// contiguous).
+    /// - `(Some(left), Some(right))` if the head is after the tail. In that case, the logical
+    /// contents of the buffer is `[left, right].concat()` (although physically the "left" slice is
+    /// stored after the "right" slice).
+    ///
+    /// Flux checks that the first slice is `None` only when the buffer is empty, that the second
+    /// one is `None` when it would be empty, and that their lengths add up to `len()`.
+    #[flux_rs::sig(
+        fn(&RingBuffer<T>[@rb])
+            -> (Option<&[T][front_len(rb)]>[!empty(rb)], Option<&[T][back_len(rb)]>[back_len(rb) > 0])
+    )]
+    #[inline(never)]
+    pub fn as_slices(&self) -> (Option<&[T]>, Option<&[T]>) {
+        assert_invariants!(self);
+        if self.head < self.tail {
+            (Some(&self.ring[self.head..self.tail]), None)
+        } else if self.head > self.tail {
+            let (left, right) = self.ring.split_at(self.head);
+            (
+                Some(right),
+                if self.tail == 0 {
+                    None
+                } else {
+                    Some(&left[..self.tail])
+                },
+            )
+        } else {
+            (None, None)
+        }
+    }
+
+    /// Removes and returns the element at logical position `index` (0 being
+    /// the front of the queue), or `None` if `index` is out of bounds.
+    ///
+    /// The hole is closed by shifting whichever side of it is shorter: the
+    /// elements before `index` move one slot towards the tail, or the elements
+    /// after it move one slot towards the head.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], usize[@idx]) -> Option<T>
+            ensures self: RingBuffer<T>{ new:
+                new.ring_len == old.ring_len
+                &&
+                (idx < len(old) => len(new) == len(old) - 1)
+                &&
+                (idx >= len(old) => new == old)
+            }
+    )]
+    #[inline(never)]
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        assert_invariants!(self);
+        let len = self.ring.len();
+        let count = queue::Queue::len(self);
+        if index >= count {
+            return None;
+        }
+
+        let slot = (self.head + index) % len;
+        let val = self.ring[slot];
+        if index < count - 1 - index {
+            // Fewer elements before the hole: move them one slot back and
+            // advance the head.
+            let mut dst = slot;
+            while dst != self.head {
+                let src = (dst + len - 1) % len;
+                self.ring[dst] = self.ring[src];
+                dst = src;
+            }
+            self.head = (self.head + 1) % len;
+        } else {
+            // Fewer elements after the hole: move them one slot forward and
+            // retract the tail.
+            let mut dst = slot;
+            let mut src = (slot + 1) % len;
+            while src != self.tail {
+                self.ring[dst] = self.ring[src];
+                dst = src;
+                src = (src + 1) % len;
+            }
+            self.tail = dst;
+        }
+        Some(val)
+    }
+
+    /// Inserts `val` at logical position `index` (0 being the front of the
+    /// queue), so that it is dequeued after `index` other elements.
+    ///
+    /// Like [`RingBuffer::remove`], this shifts whichever side of `index` is
+    /// shorter to make room. If the buffer is full or `index` is greater than
+    /// the length, the value is handed back as `Err(val)`.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], usize[@idx], _) -> Result<(), T>
+            ensures self: RingBuffer<T>{ new:
+                new.ring_len == old.ring_len
+                &&
+                ((!full(old) && idx <= len(old)) => len(new) == len(old) + 1)
+                &&
+                ((full(old) || idx > len(old)) => new == old)
+            }
+    )]
+    #[inline(never)]
+    pub fn insert(&mut self, index: usize, val: T) -> Result<(), T> {
+        assert_invariants!(self);
+        let len = self.ring.len();
+        let count = queue::Queue::len(self);
+        if queue::Queue::is_full(self) || index > count {
+            return Err(val);
+        }
+
+        let slot = (self.head + index) % len;
+        if index < count - index {
+            // Fewer elements before `index`: move them one slot back, into
+            // the free slot in front of the head.
+            let new_head = (self.head + len - 1) % len;
+            let mut dst = new_head;
+            let mut src = self.head;
+            while src != slot {
+                self.ring[dst] = self.ring[src];
+                dst = src;
+                src = (src + 1) % len;
+            }
+            self.ring[dst] = val;
+            self.head = new_head;
+        } else {
+            // Fewer elements from `index` onwards: move them one slot forward,
+            // into the free slot at the tail.
+            let mut dst = self.tail;
+            while dst != slot {
+                let src = (dst + len - 1) % len;
+                self.ring[dst] = self.ring[src];
+                dst = src;
+            }
+            self.ring[slot] = val;
+            self.tail = (self.tail + 1) % len;
+        }
+        Ok(())
+    }
+
+    /// Removes and returns the element at logical position `index`, replacing
+    /// it with the last element, or returns `None` if `index` is out of
+    /// bounds.
+    ///
+    /// This does not preserve ordering, but moves at most one element.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], usize[@idx]) -> Option<T>
+            ensures self: RingBuffer<T>{ new:
+                (idx < len(old) => new.hd == old.hd && new.tl == prev_tl(old))
+                &&
+                (idx >= len(old) => new == old)
+            }
+    )]
+    #[inline(never)]
+    pub fn swap_remove_back(&mut self, index: usize) -> Option<T> {
+        assert_invariants!(self);
+        let len = self.ring.len();
+        if index >= queue::Queue::len(self) {
+            return None;
+        }
+
+        let slot = (self.head + index) % len;
+        let last = (self.tail + len - 1) % len;
+        let val = self.ring[slot];
+        self.ring[slot] = self.ring[last];
+        self.tail = last;
+        Some(val)
+    }
+
+    /// Removes and returns the element at logical position `index`, replacing
+    /// it with the first element, or returns `None` if `index` is out of
+    /// bounds.
+    ///
+    /// This does not preserve ordering, but moves at most one element.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], usize[@idx]) -> Option<T>
+            ensures self: RingBuffer<T>{ new:
+                (idx < len(old) => new.hd == next_hd(old) && new.tl == old.tl)
+                &&
+                (idx >= len(old) => new == old)
+            }
+    )]
+    #[inline(never)]
+    pub fn swap_remove_front(&mut self, index: usize) -> Option<T> {
+        assert_invariants!(self);
+        let len = self.ring.len();
+        if index >= queue::Queue::len(self) {
+            return None;
+        }
+
+        let slot = (self.head + index) % len;
+        let val = self.ring[slot];
+        self.ring[slot] = self.ring[self.head];
+        self.head = (self.head + 1) % len;
+        Some(val)
+    }
+
+    /// Removes the first element for which the provided closure returns
+    /// `true`, without preserving the order of the remaining elements.
+    ///
+    /// Unlike [`queue::Queue::remove_first_matching`], the hole is filled by
+    /// moving the last element into it rather than by shifting every
+    /// subsequent element forward.
+    ///
+    /// If an element was removed, this function returns it as `Some(elem)`.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], _) -> Option<T>[@removed]
+            ensures self: RingBuffer<T>{ new:
+                (removed => removed_one(old, new) && new.tl == prev_tl(old))
+                &&
+                (!removed => new == old)
+            }
+    )]
+    #[inline(never)]
+    pub fn remove_first_matching_unordered<F>(&mut self, mut f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        assert_invariants!(self);
+        let len = self.ring.len();
+        let mut slot = self.head;
+        while slot != self.tail {
+            if f(&self.ring[slot]) {
+                let val = self.ring[slot];
+                let last = (self.tail + len - 1) % len;
+                self.ring[slot] = self.ring[last];
+                self.tail = last;
+                return Some(val);
+            }
+            slot = (slot + 1) % len;
+        }
+        None
+    }
+
+    /// Shortens the buffer, keeping the `len` oldest elements and dropping the
+    /// rest. This has no effect if `len` is greater than the current length.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], usize[@n])
+            ensures self: RingBuffer<T>{ new:
+                (n < len(old) => new.hd == old.hd && new.tl == (old.hd + n) % old.ring_len)
+                &&
+                (n >= len(old) => new == old)
+            }
+    )]
+    #[inline(never)]
+    pub fn truncate(&mut self, len: usize) {
+        assert_invariants!(self);
+        if len < queue::Queue::len(self) {
+            self.tail = (self.head + len) % self.ring.len();
+        }
+    }
+
+    /// Shortens the buffer, keeping the `len` newest elements and dropping the
+    /// rest. This has no effect if `len` is greater than the current length.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], usize[@n])
+            ensures self: RingBuffer<T>{ new:
+                (n < len(old) => new.tl == old.tl && new.hd == (old.tl + old.ring_len - n) % old.ring_len)
+                &&
+                (n >= len(old) => new == old)
+            }
+    )]
+    #[inline(never)]
+    pub fn truncate_front(&mut self, len: usize) {
+        assert_invariants!(self);
+        if len < queue::Queue::len(self) {
+            self.head = (self.tail + self.ring.len() - len) % self.ring.len();
+        }
+    }
+
+    /// Moves the elements from logical position `at` onwards to the back of
+    /// `other`, keeping the `at` oldest elements in this buffer.
+    ///
+    /// Returns whether the elements were moved. Nothing is moved if `at` is
+    /// greater than the length, or if `other` does not have room for all of
+    /// them.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], usize[@at], other: &strg RingBuffer<T>[@o]) -> bool[can_split(old, at, o)]
+            ensures
+                self: RingBuffer<T>{ new:
+                    (can_split(old, at, o) => new.hd == old.hd && new.tl == (old.hd + at) % old.ring_len)
+                    &&
+                    (!can_split(old, at, o) => new == old)
+                },
+                other: RingBuffer<T>{ new:
+                    (can_split(old, at, o) => new.hd == o.hd && new.tl == (o.tl + len(old) - at) % o.ring_len)
+                    &&
+                    (!can_split(old, at, o) => new == o)
+                }
+    )]
+    #[inline(never)]
+    pub fn split_off_into(&mut self, at: usize, other: &mut RingBuffer<'_, T>) -> bool {
+        assert_invariants!(self);
+        assert_invariants!(other);
+        let len = self.ring.len();
+        let count = queue::Queue::len(self);
+        if at > count || count - at > other.available_len() {
+            return false;
+        }
+
+        let split = (self.head + at) % len;
+        let mut slot = split;
+        while slot != self.tail {
+            other.ring[other.tail] = self.ring[slot];
+            other.tail = (other.tail + 1) % other.ring.len();
+            slot = (slot + 1) % len;
+        }
+        self.tail = split;
+        true
+    }
+
+    /// Returns an iterator that removes and yields every element for which
+    /// `pred` returns `true`, in queue order.
+    ///
+    /// Like [`queue::Queue::retain`], the kept elements are compacted in a
+    /// single pass as the iterator advances. If the iterator is dropped before
+    /// being exhausted, the remaining elements are kept.
+    ///
+    /// Like `Vec::extract_if`, the buffer is empty while the iterator exists,
+    /// and its elements are put back when the iterator is dropped. If the
+    /// iterator is leaked (e.g. with `mem::forget`), the buffer stays empty
+    /// rather than holding duplicated elements.
+    #[inline(never)]
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, 'a, T, F>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        assert_invariants!(self);
+        let end = self.tail;
+        self.tail = self.head;
+        ExtractIf {
+            src: self.head,
+            dst: self.head,
+            end,
+            buf: self,
+            pred,
+        }
+    }
+
+    /// Returns a [`NonEmpty`] guard if the buffer has elements, which can
+    /// then be dequeued without going through an `Option`.
+    #[inline]
+    pub fn non_empty(&mut self) -> Option<NonEmpty<'_, 'a, T>> {
+        assert_invariants!(self);
+        if self.head != self.tail {
+            Some(NonEmpty { buf: self })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a [`NotFull`] guard if the buffer has room for another
+    /// element, which can then be enqueued without checking again.
+    #[inline]
+    pub fn not_full(&mut self) -> Option<NotFull<'_, 'a, T>> {
+        assert_invariants!(self);
+        if self.head != (self.tail + 1) % self.ring.len() {
+            Some(NotFull { buf: self })
+        } else {
+            None
+        }
+    }
+}
+
+/// A slot of the ring of a [`RingBuffer`].
+///
+/// It can only be obtained from the head or the tail of a buffer, and only
+/// moves forward through `next`, wrapping around at the end of the same ring,
+/// so flux proves that indexing that ring with it is in bounds. The compiler
+/// doesn't see this proof, so the bounds checks stay in the binary unless the
+/// invariants are also assumed: the harness compares both approaches (see
+/// `ring_index.csv`).
+#[cfg(feature = "ring-index")]
+#[flux_rs::refined_by(ring_len: int, idx: int)]
+#[flux_rs::invariant(idx < ring_len)]
+#[derive(Clone, Copy)]
+struct RingIndex {
+    #[field({usize[idx] | idx < ring_len})]
+    idx: usize,
+}
+
+#[cfg(feature = "ring-index")]
+impl RingIndex {
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> RingIndex[rb.ring_len, rb.hd])]
+    #[inline(always)]
+    fn head<T: Copy>(buf: &RingBuffer<'_, T>) -> RingIndex {
+        RingIndex { idx: buf.head }
+    }
+
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> RingIndex[rb.ring_len, rb.tl])]
+    #[inline(always)]
+    fn tail<T: Copy>(buf: &RingBuffer<'_, T>) -> RingIndex {
+        RingIndex { idx: buf.tail }
+    }
+
+    #[flux_rs::sig(fn(RingIndex[@n, @i], &[T][n]) -> RingIndex[n, next_index(i, n)])]
+    #[inline(always)]
+    fn next<T>(self, ring: &[T]) -> RingIndex {
+        // Compared rather than taken modulo `ring.len()`, as `idx < ring.len()`.
+        let next = self.idx + 1;
+        RingIndex {
+            idx: if next < ring.len() { next } else { 0 },
+        }
+    }
+
+    #[flux_rs::sig(fn(RingIndex[@n, @i], &[T][n]) -> &T)]
+    #[inline(always)]
+    fn get<T>(self, ring: &[T]) -> &T {
+        &ring[self.idx]
+    }
+
+    #[flux_rs::sig(fn(RingIndex[@n, @i], &mut [T][n]) -> &mut T)]
+    #[inline(always)]
+    fn get_mut<T>(self, ring: &mut [T]) -> &mut T {
+        &mut ring[self.idx]
+    }
+}
+
+// Accesses to both ends of the ring, shared by `enqueue`, `push`, `dequeue`
+// and the `NonEmpty` and `NotFull` guards. They don't check whether the buffer
+// is empty or full. With `ring-index`, they go through a `RingIndex`.
+impl<T: Copy> RingBuffer<'_, T> {
+    // Returns the element at the head.
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> &T)]
+    #[inline(always)]
+    fn head_slot(&self) -> &T {
+        #[cfg(not(feature = "ring-index"))]
+        {
+            &self.ring[self.head]
+        }
+        #[cfg(feature = "ring-index")]
+        {
+            RingIndex::head(self).get(self.ring)
+        }
+    }
+
+    // Advances the head past the element it holds.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old])
+            ensures self: RingBuffer<T>[old.ring_len, next_hd(old), old.tl]
+    )]
+    #[inline(always)]
+    fn advance_head(&mut self) {
+        #[cfg(not(feature = "ring-index"))]
+        {
+            self.head = (self.head + 1) % self.ring.len();
+        }
+        #[cfg(feature = "ring-index")]
+        {
+            self.head = RingIndex::head(self).next(self.ring).idx;
+        }
+    }
+
+    // Removes and returns the element at the head.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old]) -> T
+            ensures self: RingBuffer<T>[old.ring_len, next_hd(old), old.tl]
+    )]
+    #[inline(always)]
+    fn pop_head(&mut self) -> T {
+        let val = *self.head_slot();
+        self.advance_head();
+        val
+    }
+
+    // Writes `val` at the tail and advances it.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], _)
+            ensures self: RingBuffer<T>[old.ring_len, old.hd, next_tl(old)]
+    )]
+    #[inline(always)]
+    fn push_tail(&mut self, val: T) {
+        #[cfg(not(feature = "ring-index"))]
+        {
+            self.ring[self.tail] = val;
+            self.tail = (self.tail + 1) % self.ring.len();
+        }
+        #[cfg(feature = "ring-index")]
+        {
+            let tail = RingIndex::tail(self);
+            *tail.get_mut(self.ring) = val;
+            self.tail = tail.next(self.ring).idx;
+        }
+    }
+}
+
+#[flux_rs::assoc(fn length(rb: RingBuffer) -> int { len(rb) })]
+#[flux_rs::assoc(fn capacity(rb: RingBuffer) -> int { rb.ring_len - 1 })]
+impl<T: Copy> queue::Queue<T> for RingBuffer<'_, T> {
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> bool[!empty(rb)]) ]
+    #[inline(never)]
+    fn has_elements(&self) -> bool {
+        assert_invariants!(self);
+        self.head != self.tail
+    }
+
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> bool[full(rb)]) ]
+    #[inline(never)]
+    fn is_full(&self) -> bool {
+        assert_invariants!(self);
+        self.head == ((self.tail + 1) % self.ring.len())
+    }
+
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> usize[len(rb)]) ]
+    #[inline(never)]
+    fn len(&self) -> usize {
+        assert_invariants!(self);
+        if self.tail > self.head {
+            self.tail - self.head
+        } else if self.tail < self.head {
+            (self.ring.len() - self.head) + self.tail
+        } else {
+            // head equals tail, length is zero
+            0
+        }
+    }
+
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> usize[available_len(rb)])]
+    #[inline(always)]
+    fn available_len(&self) -> usize {
+        RingBuffer::available_len(self)
+    }
+
+    #[flux_rs::sig(fn(&RingBuffer<T>[@rb]) -> usize[rb.ring_len - 1])]
+    #[inline(never)]
+    fn capacity(&self) -> usize {
+        assert_invariants!(self);
+        self.ring.len() - 1
+    }
+
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], _) -> bool[!full(old)]
+            ensures self: RingBuffer<T>{ new:
+                // either we're full and don't update
+                (full(old) => new.tl == old.tl && new.hd == old.hd)
+                &&
+                // or tail is incremented
+                (!full(old) => new.tl == next_tl(old) && new.hd == old.hd)
+            }
+    )]
+    #[inline(never)]
+    fn enqueue(&mut self, val: T) -> bool {
+        assert_invariants!(self);
+        if self.is_full() {
+            // Incrementing tail will overwrite head
+            false
+        } else {
+            self.push_tail(val);
+            true
+        }
+    }
+
+    #[flux_rs::sig(
+        fn(self: &strg Self[@old], _) -> Option<T>[full(old)]
+            ensures self: Self{ new:
+                // the buffer is full so we dequeue and then enqueue
+                (full(old) => (new.hd == next_hd(old) && new.tl == next_tl(old)))
+                &&
+                // or we have space so we just enqueue
+                (!full(old) => (new.tl == next_tl(old) && new.hd == old.hd))
+            }
+    )]
+    #[inline(never)]
+    fn push(&mut self, val: T) -> Option<T> {
+        assert_invariants!(self);
+        let result = if self.is_full() {
+            Some(self.pop_head())
+        } else {
+            None
+        };
+
+        self.push_tail(val);
+        result
+    }
+
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old]) -> Option<T>[!empty(old)]
+            ensures self: RingBuffer<T>{ new:
+                (empty(old) => (new == old))
+                &&
+                (!empty(old) => new.hd == next_hd(old))
+             }
+    )]
+    #[inline(never)]
+    fn dequeue(&mut self) -> Option<T> {
+        assert_invariants!(self);
+        if self.has_elements() {
+            Some(self.pop_head())
+        } else {
+            None
+        }
+    }
+
+    /// Removes the first element for which the provided closure returns `true`.
+    ///
+    /// This walks the ring buffer and, upon finding a matching element, removes
+    /// it. It then shifts all subsequent elements forward (filling the hole
+    /// created by removing the element).
+    ///
+    /// If an element was removed, this function returns it as `Some(elem)`.
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], _) -> Option<T>[@removed]
+            ensures self: RingBuffer<T>{ new:
+                (removed => removed_one(old, new))
+                &&
+                (!removed => new == old)
+            }
+    )]
+    #[flux_rs::qualifiers(SrcInRange, NextSlot)]
+    #[inline(never)]
+    fn remove_first_matching<F>(&mut self, mut f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        assert_invariants!(self);
+        let len = self.ring.len();
+        let mut slot = self.head;
+        while slot != self.tail {
+            if f(&self.ring[slot]) {
+                // This is the desired element, remove it and return it
+                let val = self.ring[slot];
+
+                let mut next_slot = (slot + 1) % len;
+                // Move everything past this element forward in the ring
+                while next_slot != self.tail {
+                    self.ring[slot] = self.ring[next_slot];
+                    slot = next_slot;
+                    assert_invariants!(self);
+                    next_slot = (next_slot + 1) % len;
+                }
+                self.tail = slot;
+                return Some(val);
+            }
+            slot = (slot + 1) % len;
+        }
+        None
+    }
+
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old]) ensures self: RingBuffer<T>[old.ring_len, 0, 0]
+    )]
+    #[inline(never)]
+    fn empty(&mut self) {
+        assert_invariants!(self);
+        self.head = 0;
+        self.tail = 0;
+    }
+
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], _) -> usize[@removed]
+            ensures self: RingBuffer<T>{ new:
+                same_head(old, new) && len(new) <= len(old) && len(new) == len(old) - removed
+            }
+    )]
+    #[inline(never)]
+    fn retain<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_mut(|x| f(x))
+    }
+
+    #[flux_rs::sig(
+        fn(self: &strg RingBuffer<T>[@old], _) -> usize[@removed]
+            ensures self: RingBuffer<T>{ new:
+                same_head(old, new) && len(new) <= len(old) && len(new) == len(old) - removed
+            }
+    )]
+    #[flux_rs::qualifiers(SrcInRange, DstTrailsSrc, RemovedCount)]
+    #[inline(never)]
+    fn retain_mut<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let len = self.ring.len();
+        assert_invariants!(self);
+        // Index over the elements before the retain operation.
+        let mut src = self.head;
+        // Index over the retained elements.
+        let mut dst = self.head;
+        let mut removed = 0;
+
+        while src != self.tail {
+            if f(&mut self.ring[src]) {
+                // When the predicate is true, move the current element to the
+                // destination if needed, and increment the destination index.
+                if src != dst {
+                    // Key assertion: dst is always valid because it trails src,
+                    // which is between head and tail. Flux proves this with the
+                    // `SrcInRange` and `DstTrailsSrc` loop invariants.
+                    assert_dst_in_bounds!(dst, len);
+                    self.ring[dst] = self.ring[src];
+                }
+                dst = (dst + 1) % len;
+            } else {
+                removed += 1;
+            }
+            src = (src + 1) % len;
+        }
+
+        self.tail = dst;
+        removed
+    }
+
+    #[flux_rs::sig(
+        fn(&RingBuffer<T>[@rb], _) -> Option<usize{i: i < len(rb)}>
+    )]
+    #[flux_rs::qualifiers(SrcInRange, IndexOfSlot)]
+    #[inline(never)]
+    fn position<F>(&self, mut f: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        assert_invariants!(self);
+        let len = self.ring.len();
+        let mut slot = self.head;
+        let mut index = 0;
+        while slot != self.tail {
+            if f(&self.ring[slot]) {
+                return Some(index);
+            }
+            slot = (slot + 1) % len;
+            index += 1;
+        }
+        None
+    }
+}
+
+/// An iterator that removes the elements matching a predicate from a
+/// [`RingBuffer`].
+///
+/// This is created by [`RingBuffer::extract_if`].
+#[flux_rs::refined_by(ring_len: int)]
+pub struct ExtractIf<'r, 'a, T: Copy, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    #[field(&mut RingBuffer<T>{rb: rb.ring_len == ring_len})]
+    buf: &'r mut RingBuffer<'a, T>,
+    // Index over the elements not visited yet.
+    #[field(usize{src: src < ring_len})]
+    src: usize,
+    // Index over the retained elements.
+    #[field(usize{dst: dst < ring_len})]
+    dst: usize,
+    // The tail of the buffer before the iterator was created, which is set
+    // back to `dst` when the iterator is dropped.
+    #[field(usize{end: end < ring_len})]
+    end: usize,
+    pred: F,
+}
+
+impl<T: Copy, F> Iterator for ExtractIf<'_, '_, T, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let buf = &mut *self.buf;
+        assert_invariants!(buf);
+        let len = buf.ring.len();
+        while self.src != self.end {
+            let val = buf.ring[self.src];
+            let src = self.src;
+            self.src = (src + 1) % len;
+            if (self.pred)(&val) {
+                return Some(val);
+            }
+            if src != self.dst {
+                buf.ring[self.dst] = val;
+            }
+            self.dst = (self.dst + 1) % len;
+        }
+        None
+    }
+}
+
+impl<T: Copy, F> Drop for ExtractIf<'_, '_, T, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    fn drop(&mut self) {
+        let buf = &mut *self.buf;
+        assert_invariants!(buf);
+        let len = buf.ring.len();
+        // Keep everything that hasn't been visited, closing the gap left by
+        // the extracted elements.
+        while self.src != self.end {
+            if self.src != self.dst {
+                buf.ring[self.dst] = buf.ring[self.src];
+            }
+            self.src = (self.src + 1) % len;
+            self.dst = (self.dst + 1) % len;
+        }
+        buf.tail = self.dst;
+    }
+}
+
+/// A [`RingBuffer`] that is known to have elements.
+///
+/// This is created by [`RingBuffer::non_empty`].
+///
+/// Its methods have no failure path, but they still index the ring: they only
+/// compile to panic-free code with `assume-invariants`, which lets the
+/// compiler drop the bounds checks that flux proves can't fail.
+pub struct NonEmpty<'r, 'a, T: Copy> {
+    // Not empty for as long as the guard exists.
+    buf: &'r mut RingBuffer<'a, T>,
+}
+
+impl<T: Copy> NonEmpty<'_, '_, T> {
+    /// Returns the element at the front of the queue.
+    #[inline]
+    pub fn front(&self) -> &T {
+        let buf = &*self.buf;
+        assert_invariants!(buf);
+        buf.head_slot()
+    }
+
+    /// Removes the element from the front of the queue.
+    #[inline]
+    pub fn dequeue(self) -> T {
+        let buf = self.buf;
+        assert_invariants!(buf);
+        buf.pop_head()
+    }
+}
+
+/// A [`RingBuffer`] that is known to have room for another element.
+///
+/// This is created by [`RingBuffer::not_full`].
+///
+/// Like [`NonEmpty`], it only compiles to panic-free code with
+/// `assume-invariants`.
+pub struct NotFull<'r, 'a, T: Copy> {
+    // Not full for as long as the guard exists.
+    buf: &'r mut RingBuffer<'a, T>,
+}
+
+impl<T: Copy> NotFull<'_, '_, T> {
+    /// Adds a new element to the back of the queue.
+    #[inline]
+    pub fn enqueue(self, val: T) {
+        let buf = self.buf;
+        assert_invariants!(buf);
+        buf.push_tail(val);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate alloc;
+
+    use super::super::queue::Queue;
+    use super::super::model::Shadowed;
+    use super::super::testing::{dequeue_iota, enqueue_iota, move_head};
+    use super::{InvariantViolation, RingBuffer};
+
+    #[test]
+    fn test_enqueue_dequeue() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        for _ in 0..2 * LEN {
+            assert!(buf.enqueue(42));
+            assert_eq!(buf.len(), 1);
+            assert!(buf.has_elements());
+
+            assert_eq!(buf.dequeue(), Some(42));
+            assert_eq!(buf.len(), 0);
+            assert!(!buf.has_elements());
+        }
+    }
+
+    #[test]
+    fn test_push() {
+        const LEN: usize = 10;
+        const MAX: usize = 100;
+        let mut ring = [0; LEN + 1];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        for i in 0..LEN {
+            assert_eq!(buf.len(), i);
+            assert!(!buf.is_full());
+            assert_eq!(buf.push(i), None);
+            assert!(buf.has_elements());
+        }
+
+        for i in LEN..MAX {
+            assert!(buf.is_full());
+            assert_eq!(buf.push(i), Some(i - LEN));
+        }
+
+        for i in 0..LEN {
+            assert!(buf.has_elements());
+            assert_eq!(buf.len(), LEN - i);
+            assert_eq!(buf.dequeue(), Some(MAX - LEN + i));
+            assert!(!buf.is_full());
+        }
+
+        assert!(!buf.has_elements());
+    }
+
+    #[test]
+    fn test_fill_once() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        assert!(!buf.has_elements());
+        assert_eq!(buf.len(), 0);
+
+        enqueue_iota(&mut buf, LEN);
+        dequeue_iota(&mut buf, LEN);
+    }
+
+    #[test]
+    fn test_refill() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        for _ in 0..10 {
+            enqueue_iota(&mut buf, LEN);
+            dequeue_iota(&mut buf, LEN);
+        }
+    }
+
+    #[test]
+    fn test_retain() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        assert_eq!(buf.retain(|x| x % 2 == 1), LEN / 2 - 1);
+        assert_eq!(buf.len(), LEN / 2);
+
+        assert_eq!(buf.dequeue(), Some(1));
+        assert_eq!(buf.dequeue(), Some(3));
+        assert_eq!(buf.dequeue(), Some(5));
+        assert_eq!(buf.dequeue(), Some(7));
+        assert_eq!(buf.dequeue(), Some(9));
+        assert_eq!(buf.dequeue(), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        assert_eq!(buf.remove(LEN), None);
+        // Near the front: shifts the head side.
+        assert_eq!(buf.remove(1), Some(2));
+        // Near the back: shifts the tail side.
+        assert_eq!(buf.remove(5), Some(7));
+        assert_eq!(buf.remove(0), Some(1));
+        assert_eq!(buf.len(), LEN - 4);
+
+        for i in [3, 4, 5, 6, 8, 9] {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert_eq!(buf.dequeue(), None);
+    }
+
+    #[test]
+    fn test_insert() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        for i in [2, 4, 6, 8] {
+            assert!(buf.enqueue(i));
+        }
+
+        assert_eq!(buf.insert(5, 10), Err(10));
+        // Near the front: shifts the head side.
+        assert_eq!(buf.insert(0, 1), Ok(()));
+        assert_eq!(buf.insert(2, 3), Ok(()));
+        // Near the back: shifts the tail side.
+        assert_eq!(buf.insert(4, 5), Ok(()));
+        assert_eq!(buf.insert(6, 7), Ok(()));
+        assert_eq!(buf.insert(8, 9), Ok(()));
+        assert!(buf.is_full());
+        assert_eq!(buf.insert(0, 0), Err(0));
+
+        for i in 1..LEN {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert_eq!(buf.dequeue(), None);
+    }
+
+    #[test]
+    fn test_swap_remove() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        assert_eq!(buf.swap_remove_back(LEN - 1), None);
+        assert_eq!(buf.swap_remove_front(LEN - 1), None);
+        assert_eq!(buf.swap_remove_back(2), Some(3));
+        assert_eq!(buf.swap_remove_front(4), Some(5));
+        // Removing the last/first element does not move anything.
+        assert_eq!(buf.swap_remove_back(buf.len() - 1), Some(8));
+        assert_eq!(buf.swap_remove_front(0), Some(2));
+        assert_eq!(buf.len(), LEN - 5);
+
+        for i in [9, 4, 1, 6, 7] {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert_eq!(buf.dequeue(), None);
+    }
+
+    #[test]
+    fn test_remove_first_matching_unordered() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        assert_eq!(buf.remove_first_matching_unordered(|&x| x == LEN), None);
+        assert_eq!(buf.remove_first_matching_unordered(|&x| x % 3 == 0), Some(3));
+        assert_eq!(buf.remove_first_matching_unordered(|&x| x % 3 == 0), Some(9));
+        assert_eq!(buf.remove_first_matching_unordered(|&x| x % 3 == 0), Some(6));
+        assert_eq!(buf.remove_first_matching_unordered(|&x| x % 3 == 0), None);
+
+        for i in [1, 2, 8, 4, 5, 7] {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert_eq!(buf.dequeue(), None);
+    }
+
+    #[test]
+    fn test_extract_if() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        let mut extracted = buf.extract_if(|x| x % 3 == 0);
+        assert_eq!(extracted.next(), Some(3));
+        assert_eq!(extracted.next(), Some(6));
+        // Dropping the iterator early keeps the unvisited 9.
+        drop(extracted);
+        assert_eq!(buf.len(), LEN - 3);
+
+        let mut extracted = buf.extract_if(|x| x % 2 == 0);
+        for i in [2, 4, 8] {
+            assert_eq!(extracted.next(), Some(i));
+        }
+        assert_eq!(extracted.next(), None);
+        drop(extracted);
+
+        for i in [1, 5, 7, 9] {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert_eq!(buf.dequeue(), None);
+
+        // Leaking the iterator leaves the buffer empty, not inconsistent.
+        enqueue_iota(&mut buf, LEN);
+        let mut extracted = buf.extract_if(|x| x % 3 == 0);
+        assert_eq!(extracted.next(), Some(3));
+        core::mem::forget(extracted);
+        assert_eq!(buf.len(), 0);
+        assert_eq!(buf.validate(), Ok(()));
+        enqueue_iota(&mut buf, LEN);
+    }
+
+    #[test]
+    fn test_remove_first_matching_stateful() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        // Remove the third odd element.
+        let mut odd = 0;
+        let removed = buf.remove_first_matching(|x| {
+            if x % 2 == 1 {
+                odd += 1;
+            }
+            odd == 3
+        });
+        assert_eq!(removed, Some(5));
+        assert_eq!(buf.len(), LEN - 2);
+    }
+
+    #[test]
+    fn test_retain_mut() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        let removed = buf.retain_mut(|x| {
+            *x *= 10;
+            *x % 20 == 0
+        });
+        assert_eq!(removed, LEN / 2);
+
+        for i in [20, 40, 60, 80] {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert_eq!(buf.dequeue(), None);
+    }
+
+    #[test]
+    fn test_position() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        assert_eq!(buf.position(|_| true), None);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        assert_eq!(buf.position(|&x| x == 1), Some(0));
+        assert_eq!(buf.position(|&x| x == 4), Some(3));
+        assert_eq!(buf.position(|&x| x == LEN - 1), Some(LEN - 2));
+        assert_eq!(buf.position(|&x| x == LEN), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        buf.truncate(LEN);
+        assert_eq!(buf.len(), LEN - 1);
+        buf.truncate(3);
+        assert_eq!(buf.len(), 3);
+        dequeue_iota(&mut buf, 4);
+    }
+
+    #[test]
+    fn test_truncate_front() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        buf.truncate_front(LEN);
+        assert_eq!(buf.len(), LEN - 1);
+        buf.truncate_front(3);
+        assert_eq!(buf.len(), 3);
+        for i in LEN - 3..LEN {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert_eq!(buf.dequeue(), None);
+    }
+
+    #[test]
+    fn test_split_off_into() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+        let mut other_ring = [0; LEN / 2];
+        let mut other = RingBuffer::new(&mut other_ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+        assert!(other.enqueue(0));
+
+        // Out of bounds, or not enough room in `other`.
+        assert!(!buf.split_off_into(LEN, &mut other));
+        assert!(!buf.split_off_into(LEN / 2, &mut other));
+        assert_eq!(buf.len(), LEN - 1);
+        assert_eq!(other.len(), 1);
+
+        assert!(buf.split_off_into(LEN / 2 + 1, &mut other));
+        dequeue_iota(&mut buf, LEN / 2 + 2);
+        for i in [0, LEN / 2 + 2, LEN / 2 + 3, LEN / 2 + 4] {
+            assert_eq!(other.dequeue(), Some(i));
+        }
+        assert_eq!(other.dequeue(), None);
+    }
+
+    #[test]
+    fn test_migrate() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+
+        // Too small to hold every element.
+        let mut small = [0; LEN - 1];
+        let Err((buf, _)) = buf.migrate(&mut small) else {
+            panic!("migrated to a buffer that is too small");
+        };
+
+        let mut large = [0; 2 * LEN];
+        let Ok(mut buf) = buf.migrate(&mut large) else {
+            panic!("failed to migrate to a larger buffer");
+        };
+        assert_eq!(buf.len(), LEN - 1);
+        assert_eq!(buf.available_len(), LEN);
+        for i in LEN..2 * LEN {
+            assert!(buf.enqueue(i));
+        }
+        assert!(buf.is_full());
+        dequeue_iota(&mut buf, 2 * LEN);
+
+        let storage = buf.reclaim();
+        assert_eq!(storage.len(), 2 * LEN);
+        assert_eq!(storage[0], 1);
+    }
+
+    #[test]
+    fn test_from_parts() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        enqueue_iota(&mut buf, LEN);
+        assert_eq!(buf.validate(), Ok(()));
+
+        let (ring, head, tail) = buf.into_parts();
+        assert_eq!((head, tail), (LEN - 2, LEN - 3));
+
+        let Err((violation, ring)) = RingBuffer::try_from_parts(ring, LEN, tail) else {
+            panic!("accepted an out of bounds head");
+        };
+        assert_eq!(violation, InvariantViolation::HeadOutOfBounds);
+        let Err((violation, ring)) = RingBuffer::try_from_parts(ring, head, LEN) else {
+            panic!("accepted an out of bounds tail");
+        };
+        assert_eq!(violation, InvariantViolation::TailOutOfBounds);
+        let Err((violation, _)) = RingBuffer::try_from_parts(&mut ring[..1], 0, 0) else {
+            panic!("accepted a ring that is too short");
+        };
+        assert_eq!(violation, InvariantViolation::RingTooShort);
+
+        let Ok(mut buf) = RingBuffer::try_from_parts(ring, head, tail) else {
+            panic!("rejected valid parts");
+        };
+        dequeue_iota(&mut buf, LEN);
+
+        let (ring, head, tail) = buf.into_parts();
+        // SAFETY: the parts come from a valid ring buffer.
+        let buf = unsafe { RingBuffer::from_parts(ring, head, tail) };
+        assert_eq!(buf.validate(), Ok(()));
+        assert!(!buf.has_elements());
+    }
+
+    #[test]
+    #[cfg(feature = "check-invariants")]
+    #[should_panic(expected = "RingBuffer invariant violated: hd < ring_len (hd = 10, ring_len = 10)")]
+    fn test_checked_invariants() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        // SAFETY: this breaks the invariants on purpose, but the
+        // `check-invariants` mode panics before they are relied upon.
+        let mut buf = unsafe { RingBuffer::from_parts(&mut ring, LEN, 0) };
+        buf.dequeue();
+    }
+
+    #[test]
+    fn test_available_len() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        move_head(&mut buf, LEN - 2);
+        for i in 0..LEN - 1 {
+            assert_eq!(buf.len(), i);
+            assert_eq!(buf.available_len(), LEN - 1 - i);
+            assert!(buf.enqueue(i));
+        }
+        assert_eq!(buf.available_len(), 0);
+        assert!(buf.is_full());
+    }
+
+    #[test]
+    fn test_as_slices() {
+        const LEN: usize = 10;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        assert_eq!(buf.as_slices(), (None, None));
+
+        enqueue_iota(&mut buf, LEN);
+        assert_eq!(buf.as_slices(), (Some(&[1, 2, 3, 4, 5, 6, 7, 8, 9][..]), None));
+
+        for i in 1..=3 {
+            assert_eq!(buf.dequeue(), Some(i));
+        }
+        assert!(buf.enqueue(10));
+        // The tail wrapped around to 0: the contents is still contiguous.
+        assert_eq!(buf.as_slices(), (Some(&[4, 5, 6, 7, 8, 9, 10][..]), None));
+        assert!(buf.enqueue(11));
+        assert_eq!(buf.as_slices(), (Some(&[4, 5, 6, 7, 8, 9, 10][..]), Some(&[11][..])));
+
+        let (front, back) = buf.as_slices();
+        let total = front.map_or(0, |s| s.len()) + back.map_or(0, |s| s.len());
+        assert_eq!(total, buf.len());
+    }
+
+    #[test]
+    fn test_fifo_model() {
+        const LEN: usize = 6;
+        for offset in 0..LEN {
+            let mut ring = [0; LEN];
+            let mut buf = RingBuffer::new(&mut ring);
+            move_head(&mut buf, offset);
+            let mut model = [0; LEN - 1];
+            let mut verdicts = [false; LEN - 1];
+            let mut buf = Shadowed::new(buf, &mut model, &mut verdicts);
+
+            for i in 1..LEN {
+                buf.enqueue(i);
+            }
+            buf.enqueue(LEN);
+            buf.push(LEN + 1);
+            buf.dequeue();
+            buf.remove_first_matching(|&x| x == 4);
+            buf.retain(|&x| x % 2 == 1);
+            buf.retain_mut(|x| {
+                *x *= 10;
+                *x != 50
+            });
+            buf.position(|&x| x == 70);
+            // Stateful predicates see every element once.
+            let mut calls = 0;
+            buf.retain(|_| {
+                calls += 1;
+                calls != 2
+            });
+            buf.push(8);
+            buf.empty();
+            buf.dequeue();
+            for i in 0..2 * LEN {
+                buf.push(i);
+                buf.remove_first_matching(|&x| x % 3 == 0);
+            }
+            while buf.has_elements() {
+                buf.dequeue();
+            }
+            assert_eq!(buf.len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_guards() {
+        const LEN: usize = 4;
+        let mut ring = [0; LEN];
+        let mut buf = RingBuffer::new(&mut ring);
+
+        assert!(buf.non_empty().is_none());
+        for i in 1..LEN {
+            buf.not_full().unwrap().enqueue(i);
+        }
+        assert!(buf.not_full().is_none());
+        assert!(buf.is_full());
+
+        for i in 1..LEN {
+            let guard = buf.non_empty().unwrap();
+            assert_eq!(*guard.front(), i);
+            assert_eq!(guard.dequeue(), i);
+        }
+        assert!(buf.non_empty().is_none());
+        assert!(buf.not_full().is_some());
+    }
+
+    crate::queue_conformance_tests!(conformance, || {
+        RingBuffer::new(alloc::vec![0usize; 10].leak())
+    });
+}
@@ -0,0 +1,312 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Helpers to test `queue::Queue` implementations, and
+//! [`queue_conformance_tests!`](crate::queue_conformance_tests), which
+//! generates the same battery of tests for any of them.
+//!
+//! This is only built for tests, or with the `testing` feature.
+
+use crate::collections::queue::Queue;
+use core::convert::From;
+use core::fmt::Debug;
+
+/// Returns the element numbered `n`, for queues of any element type that
+/// `u16` converts into.
+///
+/// The tests number their elements from 0, up to 10 times the capacity of the
+/// queue.
+pub fn elem<T: From<u16>>(n: usize) -> T {
+    T::from(u16::try_from(n).expect("element number doesn't fit in a u16"))
+}
+
+/// Enqueues the elements 1 <= n < len, checking that it succeeds and that the
+/// queue is full at the end.
+///
+/// See `std::iota` in C++.
+pub fn enqueue_iota<T, Q>(buf: &mut Q, len: usize)
+where
+    T: From<u16>,
+    Q: Queue<T>,
+{
+    for i in 1..len {
+        assert!(!buf.is_full());
+        assert!(buf.enqueue(elem(i)));
+        assert!(buf.has_elements());
+        assert_eq!(buf.len(), i);
+    }
+
+    assert!(buf.is_full());
+    assert!(!buf.enqueue(elem(0)));
+    assert!(buf.has_elements());
+}
+
+/// Dequeues all elements, expecting the elements 1 <= n < len, and checks
+/// that the queue is empty at the end.
+///
+/// See `std::iota` in C++.
+pub fn dequeue_iota<T, Q>(buf: &mut Q, len: usize)
+where
+    T: From<u16> + PartialEq + Debug,
+    Q: Queue<T>,
+{
+    for i in 1..len {
+        assert!(buf.has_elements());
+        assert_eq!(buf.len(), len - i);
+        assert_eq!(buf.dequeue(), Some(elem(i)));
+        assert!(!buf.is_full());
+    }
+
+    assert!(!buf.has_elements());
+    assert_eq!(buf.len(), 0);
+}
+
+/// Moves the head by `count` elements, by enqueueing and dequeueing an
+/// element `count` times.
+///
+/// The queue must be empty at the beginning, and is empty at the end.
+pub fn move_head<T, Q>(buf: &mut Q, count: usize)
+where
+    T: From<u16> + PartialEq + Debug,
+    Q: Queue<T>,
+{
+    assert!(!buf.has_elements());
+    assert_eq!(buf.len(), 0);
+
+    for _ in 0..count {
+        assert!(buf.enqueue(elem(0)));
+        assert_eq!(buf.dequeue(), Some(elem(0)));
+    }
+
+    assert!(!buf.has_elements());
+    assert_eq!(buf.len(), 0);
+}
+
+/// Returns how many elements the empty queue `buf` can hold, by filling it.
+pub fn capacity<T, Q>(mut buf: Q) -> usize
+where
+    T: From<u16>,
+    Q: Queue<T>,
+{
+    let mut capacity = 0;
+    while buf.enqueue(elem(0)) {
+        capacity += 1;
+    }
+    capacity
+}
+
+/// Generates a module `$name` with the conformance tests of `queue::Queue`.
+///
+/// `$constructor` is called to create each queue, which must be an empty
+/// `Queue<T>` with room for at least 2 elements, and for few enough that 10
+/// times as many fit in a `u16` (see [`elem`]). `T` can be any type that `u16`
+/// converts into, and that is `PartialEq` and `Debug`. The
+/// constructor is expanded inside the module, which imports everything from
+/// the enclosing one.
+///
+/// ```ignore
+/// queue_conformance_tests!(array_ring_buffer8, || ArrayRingBuffer8::new([0u32; 10]));
+/// ```
+#[macro_export]
+macro_rules! queue_conformance_tests {
+    ($name:ident, $constructor:expr) => {
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+            use $crate::collections::queue::Queue;
+            use $crate::collections::testing::{
+                capacity, dequeue_iota, elem, enqueue_iota, move_head,
+            };
+
+            // The argument of `enqueue_iota` and `dequeue_iota` that fills the
+            // queue.
+            fn iota_len() -> usize {
+                capacity(($constructor)()) + 1
+            }
+
+            #[test]
+            fn test_enqueue_dequeue() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                for _ in 0..2 * len {
+                    assert!(buf.enqueue(elem(42)));
+                    assert_eq!(buf.len(), 1);
+                    assert!(buf.has_elements());
+
+                    assert_eq!(buf.dequeue(), Some(elem(42)));
+                    assert_eq!(buf.len(), 0);
+                    assert!(!buf.has_elements());
+                }
+            }
+
+            #[test]
+            fn test_push() {
+                let len = iota_len() - 1;
+                let max = 10 * len;
+                let mut buf = ($constructor)();
+
+                for i in 0..len {
+                    assert_eq!(buf.len(), i);
+                    assert!(!buf.is_full());
+                    assert_eq!(buf.push(elem(i)), None);
+                    assert!(buf.has_elements());
+                }
+
+                for i in len..max {
+                    assert!(buf.is_full());
+                    assert_eq!(buf.push(elem(i)), Some(elem(i - len)));
+                }
+
+                for i in 0..len {
+                    assert!(buf.has_elements());
+                    assert_eq!(buf.len(), len - i);
+                    assert_eq!(buf.dequeue(), Some(elem(max - len + i)));
+                    assert!(!buf.is_full());
+                }
+
+                assert!(!buf.has_elements());
+            }
+
+            #[test]
+            fn test_fill_once() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                assert!(!buf.has_elements());
+                assert_eq!(buf.len(), 0);
+
+                enqueue_iota(&mut buf, len);
+                dequeue_iota(&mut buf, len);
+            }
+
+            #[test]
+            fn test_refill() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                for _ in 0..10 {
+                    enqueue_iota(&mut buf, len);
+                    dequeue_iota(&mut buf, len);
+                }
+            }
+
+            #[test]
+            fn test_every_head() {
+                let len = iota_len();
+                for count in 0..len {
+                    let mut buf = ($constructor)();
+                    move_head(&mut buf, count);
+                    enqueue_iota(&mut buf, len);
+                    dequeue_iota(&mut buf, len);
+                }
+            }
+
+            #[test]
+            fn test_available_len() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                assert_eq!(buf.capacity(), len - 1);
+                move_head(&mut buf, len / 2);
+                for i in 0..len - 1 {
+                    assert_eq!(buf.available_len(), len - 1 - i);
+                    assert!(buf.enqueue(elem(i)));
+                }
+                assert_eq!(buf.available_len(), 0);
+                assert!(buf.is_full());
+                assert_eq!(buf.capacity(), len - 1);
+            }
+
+            #[test]
+            fn test_empty() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                move_head(&mut buf, len / 2);
+                enqueue_iota(&mut buf, len);
+                buf.empty();
+                assert!(!buf.has_elements());
+                assert_eq!(buf.len(), 0);
+                assert_eq!(buf.dequeue(), None);
+                enqueue_iota(&mut buf, len);
+            }
+
+            #[test]
+            fn test_remove_first_matching() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                move_head(&mut buf, len - 2);
+                enqueue_iota(&mut buf, len);
+
+                assert_eq!(buf.remove_first_matching(|x| *x == elem(len)), None);
+                let even = buf.remove_first_matching(|x| {
+                    (2..len).step_by(2).any(|i| *x == elem(i))
+                });
+                assert_eq!(even, Some(elem(2)));
+                assert_eq!(buf.remove_first_matching(|x| *x == elem(1)), Some(elem(1)));
+                assert_eq!(buf.len(), len - 3);
+                for i in 3..len {
+                    assert_eq!(buf.dequeue(), Some(elem(i)));
+                }
+                assert_eq!(buf.dequeue(), None);
+            }
+
+            #[test]
+            fn test_retain() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                move_head(&mut buf, len - 2);
+                enqueue_iota(&mut buf, len);
+
+                let removed = buf.retain(|x| (1..len).step_by(2).any(|i| *x == elem(i)));
+                assert_eq!(removed, (len - 1) / 2);
+                assert_eq!(buf.len(), len / 2);
+
+                for i in (1..len).step_by(2) {
+                    assert_eq!(buf.dequeue(), Some(elem(i)));
+                }
+                assert_eq!(buf.dequeue(), None);
+            }
+
+            #[test]
+            fn test_retain_mut() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                move_head(&mut buf, len - 2);
+                enqueue_iota(&mut buf, len);
+
+                // Multiplies each element by 10, and keeps the even ones.
+                let removed = buf.retain_mut(|x| {
+                    let i = (1..len).find(|&i| *x == elem(i)).unwrap();
+                    *x = elem(10 * i);
+                    i % 2 == 0
+                });
+                assert_eq!(removed, len / 2);
+
+                for i in (2..len).step_by(2) {
+                    assert_eq!(buf.dequeue(), Some(elem(10 * i)));
+                }
+                assert_eq!(buf.dequeue(), None);
+            }
+
+            #[test]
+            fn test_position() {
+                let len = iota_len();
+                let mut buf = ($constructor)();
+
+                assert_eq!(buf.position(|_| true), None);
+
+                move_head(&mut buf, len - 2);
+                enqueue_iota(&mut buf, len);
+
+                assert_eq!(buf.position(|x| *x == elem(1)), Some(0));
+                assert_eq!(buf.position(|x| *x == elem(len - 1)), Some(len - 2));
+                assert_eq!(buf.position(|x| *x == elem(len)), None);
+            }
+        }
+    };
+}
@@ -0,0 +1,350 @@
+#![no_std]
+#![no_main]
+
+use ring_buffer_smoketest::collections::array_ring_buffer::{ArrayRingBuffer16, ArrayRingBuffer8};
+use ring_buffer_smoketest::collections::queue::Queue;
+use ring_buffer_smoketest::collections::ring_buffer::RingBuffer;
+use core::hint::black_box;
+use core::mem::size_of;
+use core::panic::PanicInfo;
+
+// This function is called on panic
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    loop {}
+}
+
+macro_rules! harness_fn {
+    ($name:ident, $body:expr) => {
+        #[no_mangle]
+        pub fn $name(buf: &mut RingBuffer<i32>) {
+            $body(buf);
+        }
+    };
+}
+
+// Define all the wrappers
+harness_fn!(call_available_len, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.available_len());
+});
+
+harness_fn!(call_as_slices, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.as_slices());
+});
+
+harness_fn!(call_has_elements, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.has_elements());
+});
+
+harness_fn!(call_is_full, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.is_full());
+});
+
+harness_fn!(call_len, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.len());
+});
+
+harness_fn!(call_enqueue, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.enqueue(black_box(1)));
+});
+
+harness_fn!(call_dequeue, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.dequeue());
+});
+
+harness_fn!(call_push, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.push(black_box(2)));
+});
+
+harness_fn!(call_remove_first_matching, |buf: &mut RingBuffer<i32>| {
+    let _ = buf.enqueue(3);
+    black_box(buf.remove_first_matching(|&x: &i32| x == 3));
+});
+
+harness_fn!(call_retain, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.retain(|&x: &i32| black_box(x) * black_box(2) == 0));
+});
+
+harness_fn!(call_retain_mut, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.retain_mut(|x: &mut i32| {
+        *x += black_box(1);
+        black_box(*x) * black_box(2) == 0
+    }));
+});
+
+harness_fn!(call_position, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.position(|&x: &i32| x == black_box(3)));
+});
+
+harness_fn!(call_empty, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.empty());
+});
+
+harness_fn!(call_remove, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.remove(black_box(1)));
+});
+
+harness_fn!(call_insert, |buf: &mut RingBuffer<i32>| {
+    let _ = black_box(buf.insert(black_box(1), black_box(4)));
+});
+
+harness_fn!(call_swap_remove_back, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.swap_remove_back(black_box(1)));
+});
+
+harness_fn!(call_swap_remove_front, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.swap_remove_front(black_box(1)));
+});
+
+harness_fn!(call_remove_first_matching_unordered, |buf: &mut RingBuffer<i32>| {
+    let _ = buf.enqueue(3);
+    black_box(buf.remove_first_matching_unordered(|&x: &i32| x == 3));
+});
+
+harness_fn!(call_truncate, |buf: &mut RingBuffer<i32>| {
+    buf.truncate(black_box(1));
+    black_box(&mut *buf);
+});
+
+harness_fn!(call_truncate_front, |buf: &mut RingBuffer<i32>| {
+    buf.truncate_front(black_box(1));
+    black_box(&mut *buf);
+});
+
+harness_fn!(call_split_off_into, |buf: &mut RingBuffer<i32>| {
+    let mut storage = [0; 5];
+    let mut other = RingBuffer::new(&mut storage);
+    black_box(buf.split_off_into(black_box(1), &mut other));
+    black_box(&mut other);
+});
+
+harness_fn!(call_migrate, |_buf: &mut RingBuffer<i32>| {
+    let mut storage = [0; 5];
+    let mut grown = [0; 8];
+    let small = black_box(RingBuffer::new(&mut storage));
+    black_box(small.migrate(&mut grown).ok());
+});
+
+harness_fn!(call_reclaim, |_buf: &mut RingBuffer<i32>| {
+    let mut storage = [0; 5];
+    let small = black_box(RingBuffer::new(&mut storage));
+    black_box(small.reclaim());
+});
+
+harness_fn!(call_validate, |buf: &mut RingBuffer<i32>| {
+    black_box(buf.validate().is_ok());
+});
+
+harness_fn!(call_try_from_parts, |_buf: &mut RingBuffer<i32>| {
+    let mut storage = [0; 5];
+    black_box(RingBuffer::try_from_parts(&mut storage, black_box(1), black_box(3)).is_ok());
+});
+
+harness_fn!(call_extract_if, |buf: &mut RingBuffer<i32>| {
+    for val in buf.extract_if(|&x: &i32| black_box(x) * black_box(2) == 0) {
+        black_box(val);
+    }
+});
+
+// The typestate guards should compile to panic-free code with
+// `assume-invariants`, which `generate_artifacts.py` checks. The other builds
+// keep the bounds checks of the ring accesses.
+harness_fn!(call_non_empty_front, |buf: &mut RingBuffer<i32>| {
+    if let Some(guard) = buf.non_empty() {
+        black_box(*guard.front());
+    }
+});
+
+harness_fn!(call_non_empty_dequeue, |buf: &mut RingBuffer<i32>| {
+    if let Some(guard) = buf.non_empty() {
+        black_box(guard.dequeue());
+    }
+});
+
+harness_fn!(call_not_full_enqueue, |buf: &mut RingBuffer<i32>| {
+    if let Some(guard) = buf.not_full() {
+        guard.enqueue(black_box(5));
+    }
+});
+
+// Moves the front element to the back, as a round-robin scheduler would.
+// This is verified for any queue from the `Queue` contracts: `dequeue` frees a
+// slot, so the `enqueue` that follows can't fail, and the `ensures` clause
+// only holds if it doesn't. The result is not asserted, which would bring in
+// the panic machinery. The contracts don't say that a queue never holds more
+// than its capacity, so the caller has to show it (flux does from
+// `len(rb) < rb.ring_len` for a `RingBuffer`).
+#[flux_rs::sig(
+    fn(q: &strg Q[@old])
+        requires <Q as Queue<i32>>::length(old) <= <Q as Queue<i32>>::capacity(old)
+        ensures q: Q{ new: <Q as Queue<i32>>::length(new) == <Q as Queue<i32>>::length(old) }
+)]
+#[inline(never)]
+fn requeue<Q: Queue<i32>>(q: &mut Q) {
+    if let Some(val) = q.dequeue() {
+        let requeued = q.enqueue(val);
+        black_box(requeued);
+    }
+}
+
+harness_fn!(call_requeue, |_buf: &mut RingBuffer<i32>| {
+    let mut storage = [0; 5];
+    let mut local = RingBuffer::new(&mut storage);
+    let _ = local.enqueue(black_box(1));
+    requeue(&mut local);
+    black_box(&mut local);
+});
+
+// Calls every method of a ring buffer that owns its storage, so that each of
+// them ends up in the binary (as `ArrayRingBuffer8::enqueue`, ...) to be
+// compared with the `RingBuffer` ones.
+macro_rules! call_array_ring_buffer {
+    ($buf:expr) => {
+        let mut buf = black_box($buf);
+        black_box(buf.available_len());
+        black_box(buf.as_slices());
+        black_box(buf.has_elements());
+        black_box(buf.is_full());
+        black_box(buf.len());
+        black_box(buf.enqueue(black_box(1)));
+        black_box(buf.dequeue());
+        black_box(buf.push(black_box(2)));
+        black_box(buf.remove_first_matching(|&x: &i32| x == black_box(3)));
+        black_box(buf.retain(|&x: &i32| black_box(x) * black_box(2) == 0));
+        black_box(buf.retain_mut(|x: &mut i32| {
+            *x += black_box(1);
+            black_box(*x) * black_box(2) == 0
+        }));
+        black_box(buf.position(|&x: &i32| x == black_box(3)));
+        black_box(buf.empty());
+        black_box(&mut buf);
+    };
+}
+
+harness_fn!(call_array_ring_buffer8, |_buf: &mut RingBuffer<i32>| {
+    call_array_ring_buffer!(ArrayRingBuffer8::new([0; ARRAY_LEN]));
+});
+
+harness_fn!(call_array_ring_buffer16, |_buf: &mut RingBuffer<i32>| {
+    call_array_ring_buffer!(ArrayRingBuffer16::new([0; ARRAY_LEN]));
+});
+
+// Length of the rings in the `ArrayRingBuffer` harness functions.
+const ARRAY_LEN: usize = 16;
+
+// The size of each ring buffer struct, which `generate_artifacts.py` reads from
+// the size of these symbols. The rings of `ArrayRingBuffer*` are part of the
+// struct, while `RingBuffer` only points to its ring.
+#[used]
+#[no_mangle]
+pub static SIZE_OF_RING_BUFFER: [u8; size_of::<RingBuffer<i32>>()] = [0; size_of::<RingBuffer<i32>>()];
+#[used]
+#[no_mangle]
+pub static SIZE_OF_RING: [u8; size_of::<[i32; ARRAY_LEN]>()] = [0; size_of::<[i32; ARRAY_LEN]>()];
+#[used]
+#[no_mangle]
+pub static SIZE_OF_ARRAY_RING_BUFFER8: [u8; size_of::<ArrayRingBuffer8<i32, ARRAY_LEN>>()] =
+    [0; size_of::<ArrayRingBuffer8<i32, ARRAY_LEN>>()];
+#[used]
+#[no_mangle]
+pub static SIZE_OF_ARRAY_RING_BUFFER16: [u8; size_of::<ArrayRingBuffer16<i32, ARRAY_LEN>>()] =
+    [0; size_of::<ArrayRingBuffer16<i32, ARRAY_LEN>>()];
+
+// A simple function that just returns 3
+#[no_mangle]
+pub extern "C" fn foo() -> i32 {
+    3
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> !{
+    const LEN: usize = 5;
+    let mut storage = [0; LEN];
+    let mut buf = RingBuffer::new(&mut storage);
+
+    call_available_len(&mut buf);
+    call_as_slices(&mut buf);
+    call_has_elements(&mut buf);
+    call_is_full(&mut buf);
+    call_len(&mut buf);
+    call_enqueue(&mut buf);
+    call_dequeue(&mut buf);
+    call_push(&mut buf);
+    call_remove_first_matching(&mut buf);
+    call_retain(&mut buf);
+    call_retain_mut(&mut buf);
+    call_position(&mut buf);
+    call_empty(&mut buf);
+    call_remove(&mut buf);
+    call_insert(&mut buf);
+    call_swap_remove_back(&mut buf);
+    call_swap_remove_front(&mut buf);
+    call_remove_first_matching_unordered(&mut buf);
+    call_truncate(&mut buf);
+    call_truncate_front(&mut buf);
+    call_split_off_into(&mut buf);
+    call_migrate(&mut buf);
+    call_reclaim(&mut buf);
+    call_validate(&mut buf);
+    call_try_from_parts(&mut buf);
+    call_extract_if(&mut buf);
+    call_requeue(&mut buf);
+    call_non_empty_front(&mut buf);
+    call_non_empty_dequeue(&mut buf);
+    call_not_full_enqueue(&mut buf);
+    call_array_ring_buffer8(&mut buf);
+    call_array_ring_buffer16(&mut buf);
+    loop {}
+}
+
+
+#[link(name="c")]
+extern "C" {
+}
+
+#[no_mangle]
+pub static TEST_FUNCS: [fn(&mut RingBuffer<i32>); 32] = [
+    call_available_len,
+    call_as_slices,
+    call_has_elements,
+    call_is_full,
+    call_len,
+    call_enqueue,
+    call_dequeue,
+    call_push,
+    call_remove_first_matching,
+    call_retain,
+    call_retain_mut,
+    call_position,
+    call_empty,
+    call_remove,
+    call_insert,
+    call_swap_remove_back,
+    call_swap_remove_front,
+    call_remove_first_matching_unordered,
+    call_truncate,
+    call_truncate_front,
+    call_split_off_into,
+    call_migrate,
+    call_reclaim,
+    call_validate,
+    call_try_from_parts,
+    call_extract_if,
+    call_requeue,
+    call_non_empty_front,
+    call_non_empty_dequeue,
+    call_not_full_enqueue,
+    call_array_ring_buffer8,
+    call_array_ring_buffer16,
+];
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    main();
+}
+
+// For arm to not complain
+#[no_mangle]
+pub extern "C" fn __aeabi_unwind_cpp_pr0() {}
+#[no_mangle]
+pub extern "C" fn __aeabi_unwind_cpp_pr1() {}
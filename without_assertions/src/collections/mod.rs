@@ -1,2 +0,0 @@
-pub mod queue;
-pub mod ring_buffer;
@@ -1,9 +0,0 @@
-#![no_std]
-use flux_rs::attrs::*;
-
-pub mod collections;
-
-// #[flux::spec(fn(x: i32) -> i32{v: x < v})]
-// fn inc(x: i32) -> i32 {
-//     x + 1
-// }